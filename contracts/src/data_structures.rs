@@ -1,32 +1,414 @@
+use crate::errors::ContractError;
 use near_sdk::{
     borsh::{self, BorshDeserialize, BorshSerialize},
     env,
-    collections::LookupMap,
+    collections::{LookupMap, LookupSet, UnorderedMap, Vector},
+    serde::{Deserialize, Serialize},
     BorshStorageKey,
     AccountId,
+    Balance,
+    StorageUsage,
     json_types::U128,
 };
 
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct Profile {
+    /// number the collections are prefixed with, so a profile added again after
+    /// `storage_unregister` starts empty. 0 for profiles from the first deployment
+    pub nonce: u64,
     pub profile_type: ProfileType,
     pub payment_interval: i32,
     pub content_count: LookupMap<String, i32>,
+    /// plain `String` content added before `ContentItem` existed, read only
     pub content: LookupMap<String, String>,
+    pub content_items: LookupMap<String, ContentItem>,
+    /// content ids in the order they were first added
+    pub content_ids: Vector<String>,
     pub subscribers: LookupMap<AccountId, i32>,
-    pub costs: LookupMap<String, U128>
+    pub costs: LookupMap<String, U128>,
+    /// extra pricing tiers, maps a number of pieces of content to its cost
+    pub bundles: UnorderedMap<i32, U128>,
+    pub access_mode: AccessMode,
+    /// block timestamp each subscriber's access ends at, used by `AccessMode::Period`
+    pub expiries: LookupMap<AccountId, u64>,
+    /// number of accounts that have ever subscribed
+    pub subscriber_count: u32,
+    /// longest the creator commits to go between posts
+    pub max_post_gap: Option<PostGap>,
+    /// block height of the most recent post, `None` before the first one
+    pub last_published_block: Option<u64>,
+    /// last NEAR payment of each subscriber made under a `max_post_gap` commitment
+    pub payments: LookupMap<AccountId, Payment>,
+    /// accounts subscription payments are split between, empty when the creator gets it all
+    pub payees: Vec<Payee>,
+    /// who can read each piece of content, missing entries are `Visibility::SubscribersOnly`
+    pub content_visibility: LookupMap<String, Visibility>,
+    /// each account and piece of content it has bought on its own
+    pub purchases: LookupSet<(AccountId, String)>,
+    /// pieces of content a new subscriber can read for free
+    pub trial_posts: Option<i32>,
+    /// content count each account's free trial lasts until, kept once it ends
+    pub trials: LookupMap<AccountId, i32>,
+    /// discounts keyed by the sha256 hash of their code, so the codes can't be read from state
+    pub promo_codes: LookupMap<Vec<u8>, PromoCode>
 }
 
+/// The JSON representation of a `Profile` returned by `get_profile`
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ProfileView {
+    pub profile_type: ProfileType,
+    /// cost of the default tier in yoctoNEAR
+    pub cost: U128,
+    pub payment_interval: i32,
+    pub access_mode: AccessMode,
+    pub content_count: i32,
+    pub subscriber_count: u32
+}
+
+/// How long a subscription payment grants access for
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum AccessMode {
+    /// access to `payment_interval` more pieces of content
+    ContentCount,
+    /// access for `duration` nanoseconds of block time
+    Period { duration: u64 }
+}
+
+impl AccessMode {
+    pub fn new(access_mode: &str, period_days: Option<u64>) -> Self {
+        match (access_mode, period_days) {
+            ("content_count", _) => Self::ContentCount,
+            ("period", Some(days)) if days > 0 => match days.checked_mul(24 * 60 * 60 * 1_000_000_000) {
+                Some(duration) => Self::Period { duration },
+                None => ContractError::InvalidInput(format!("a period of {} days is too long", days)).panic()
+            },
+            ("period", _) => ContractError::InvalidInput(
+                "a period subscription needs a period of at least one day".to_owned()
+            ).panic(),
+            _ => ContractError::InvalidInput("enter a valid access mode".to_owned()).panic()
+        }
+    }
+}
+
+/// Longest a creator commits to go between posts, in block time or in blocks
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum PostGap {
+    Nanoseconds(u64),
+    Blocks(u64)
+}
+
+impl PostGap {
+    /// Returns whether the gap has passed since the block with timestamp `timestamp` at `block_height`
+    pub fn has_passed(&self, timestamp: u64, block_height: u64) -> bool {
+        match self {
+            Self::Nanoseconds(gap) => env::block_timestamp() > timestamp.saturating_add(*gap),
+            Self::Blocks(gap) => env::block_height() > block_height.saturating_add(*gap)
+        }
+    }
+}
+
+/// A piece of content published by a creator
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ContentItem {
+    pub title: String,
+    /// the content itself, e.g. markdown, or a URI to privately hosted media
+    pub body: String,
+    pub mime_type: String,
+    /// block timestamp the content was added at
+    pub created_at: u64,
+    /// the profiles content count once this was added, starting at 1
+    pub sequence: i32,
+    /// hash of the media `body` points to, so subscribers can verify it
+    pub content_hash: Option<String>
+}
+
+/// The public details of a `ContentItem`, without its gated `body`
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ContentMetadata {
+    pub id: String,
+    pub title: String,
+    pub sequence: i32,
+    pub created_at: u64,
+    pub visibility: Visibility
+}
+
+/// Who can read a piece of content besides its creator and their subscribers
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum Visibility {
+    /// anyone can read it
+    Public,
+    /// anyone can read `excerpt` in place of the body
+    Preview { excerpt: String },
+    SubscribersOnly
+}
+
+/// The fields of a `ContentItem` supplied by the creator
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NewContent {
+    pub title: String,
+    pub body: String,
+    pub mime_type: String,
+    pub content_hash: Option<String>,
+    /// defaults to `Visibility::SubscribersOnly`
+    #[serde(default)]
+    pub visibility: Option<Visibility>
+}
+
+impl ContentItem {
+    pub fn new(content: NewContent, sequence: i32) -> Self {
+        Self {
+            title: content.title,
+            body: content.body,
+            mime_type: content.mime_type,
+            created_at: env::block_timestamp(),
+            sequence,
+            content_hash: content.content_hash
+        }
+    }
+
+    /// Wraps content stored as a plain `String`, which has no metadata
+    pub fn from_legacy(body: String) -> Self {
+        Self {
+            title: String::new(),
+            body,
+            mime_type: "text/plain".to_owned(),
+            created_at: 0,
+            sequence: 0,
+            content_hash: None
+        }
+    }
+}
+
+/// A subscription payment that can be partly refunded if the creator stops publishing
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Payment {
+    /// yoctoNEAR paid
+    pub amount: Balance,
+    /// pieces of content the payment was for
+    pub credit: i32,
+    /// the profiles content count when the payment was made
+    pub content_count: i32,
+    /// block timestamp of the payment
+    pub paid_at: u64,
+    /// block height of the payment
+    pub paid_at_block: u64,
+    /// the creators `max_post_gap` when the payment was made
+    pub max_post_gap: PostGap,
+    /// the creators payees when the payment was made, a refund is taken back from the same split
+    pub payees: Vec<Payee>
+}
+
+/// The subscription a pass was minted for, whoever owns the pass has its access
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Pass {
+    pub creator: AccountId,
+    /// nonce of the creators profile, a pass gives no access to a profile added after it was removed
+    pub profile_nonce: u64,
+    /// the creators content count when the pass was minted
+    pub content_count: i32,
+    /// pieces of content after `content_count` the pass gives access to
+    pub payment_interval: i32,
+    /// block timestamp access ends at for `AccessMode::Period` subscriptions
+    pub expires_at: Option<u64>
+}
+
+/// An account that gets `share_bps` basis points of each subscription payment
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Payee {
+    pub account_id: AccountId,
+    pub share_bps: u16
+}
+
+/// Basis points in a whole payment
+pub const TOTAL_SHARE_BPS: u16 = 10_000;
+
+/// A discount subscribers get by passing its code to `subscribe`
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PromoCode {
+    pub discount: Discount,
+    /// times the code can be used across all subscribers
+    pub max_uses: u32,
+    pub uses: u32,
+    /// last block height the code can be used at
+    pub expires_at_block: u64
+}
+
+/// How much a `PromoCode` takes off the cost of a subscription
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum Discount {
+    /// whole percent off, from 1 to 100
+    Percent(u8),
+    /// yoctoNEAR off, the cost can't go below zero
+    Fixed(U128)
+}
+
+impl Discount {
+    /// Returns `cost` with the discount taken off
+    pub fn apply(&self, cost: Balance) -> Balance {
+        match self {
+            Self::Percent(percent) => cost - cost * Balance::from(*percent) / 100,
+            Self::Fixed(amount) => cost.saturating_sub(amount.0)
+        }
+    }
+}
+
+/// A pricing tier: `content_count` pieces of content for `cost` yoctoNEAR
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Bundle {
+    pub content_count: i32,
+    pub cost: U128
+}
+
+/// Storage prefixes for the contract's collections
+///
+/// `Content`, `Subscribers`, `Cost` and `ContentCount` are the legacy prefixes
+/// that were shared by every profile, they are only kept so `migrate_profile`
+/// can read old state. New profiles use the `Profile*` variants, which are
+/// prefixed with a hash of the owners `AccountId` and the profiles nonce. `Data` holds profiles stored
+/// before they were versioned, newer ones are stored under `Profiles`
+#[allow(dead_code)]
 #[derive(BorshStorageKey, BorshSerialize)]
 pub enum StorageKeys {
     Data,
     Content,
     Subscribers,
     Cost,
-    ContentCount
+    ContentCount,
+    ProfileContent { account_hash: Vec<u8> },
+    ProfileSubscribers { account_hash: Vec<u8> },
+    ProfileCost { account_hash: Vec<u8> },
+    ProfileContentCount { account_hash: Vec<u8> },
+    StorageAccounts,
+    ProfileBundles { account_hash: Vec<u8> },
+    ProfileExpiries { account_hash: Vec<u8> },
+    AcceptedTokens,
+    ProfileContentItems { account_hash: Vec<u8> },
+    ProfileContentIds { account_hash: Vec<u8> },
+    Profiles,
+    Admins,
+    CreatorBalances,
+    ProfilePayments { account_hash: Vec<u8> },
+    Passes,
+    PassMetadata,
+    PassesPerOwner,
+    PassTerms,
+    ProfileContentVisibility { account_hash: Vec<u8> },
+    ProfilePurchases { account_hash: Vec<u8> },
+    ProfileTrials { account_hash: Vec<u8> },
+    ProfilePromoCodes { account_hash: Vec<u8> },
+    Subscriptions,
+    SubscriptionCreators { account_hash: Vec<u8> },
+    TokenBalances,
+    HeldPayments
 }
 
-#[derive(BorshDeserialize, BorshSerialize, PartialEq, Debug)]
+/// An accounts NEP-145 storage registration
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct StorageAccount {
+    /// yoctoNEAR deposited for storage
+    pub deposit: Balance,
+    /// bytes of contract storage this account is paying for
+    pub bytes_used: StorageUsage
+}
+
+impl StorageAccount {
+    /// yoctoNEAR locked to cover `bytes_used`
+    pub fn locked(&self) -> Balance {
+        Balance::from(self.bytes_used) * env::storage_byte_cost()
+    }
+
+    /// yoctoNEAR that can still be spent on storage or withdrawn
+    pub fn available(&self) -> Balance {
+        self.deposit.saturating_sub(self.locked())
+    }
+}
+
+/// Subscription payments a creator has earned, held by the contract until withdrawn
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct CreatorBalance {
+    /// yoctoNEAR received and not yet withdrawn
+    pub balance: Balance,
+    /// yoctoNEAR of withdrawals whose transfer hasn't resolved yet
+    pub pending: Balance
+}
+
+impl CreatorBalance {
+    /// yoctoNEAR that can be withdrawn now
+    pub fn withdrawable(&self) -> Balance {
+        self.balance - self.pending
+    }
+}
+
+/// The platform fee settings returned by `get_platform_fee`
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PlatformFeeView {
+    pub fee_bps: u16,
+    /// yoctoNEAR of fees the owner can withdraw now
+    pub treasury_balance: U128,
+    /// yoctoNEAR of fees collected in total
+    pub fees_collected: U128
+}
+
+/// One of an accounts subscriptions, returned by `get_subscriptions`
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SubscriptionView {
+    pub creator: AccountId,
+    /// pieces of content left on content count subscriptions, zero or below once it has run out
+    pub remaining_credit: Option<i32>,
+    /// block timestamp access ends at on period subscriptions
+    pub expires_at: Option<u64>
+}
+
+/// Divides `amount` between `payees`, the rounding remainder goes to `creator_address`
+pub(crate) fn split_between(payees: &[Payee], creator_address: &AccountId, amount: Balance) -> Vec<(AccountId, Balance)> {
+    let mut split: Vec<(AccountId, Balance)> = payees
+        .iter()
+        .map(|payee| (
+            payee.account_id.clone(),
+            amount * payee.share_bps as Balance / TOTAL_SHARE_BPS as Balance
+        ))
+        .collect();
+    let remainder = amount - split.iter().map(|(_, share)| share).sum::<Balance>();
+    match split.iter_mut().find(|(account_id, _)| account_id == creator_address) {
+        Some((_, share)) => *share += remainder,
+        None if remainder > 0 => split.push((creator_address.clone(), remainder)),
+        None => {}
+    }
+    split
+}
+
+/// returns the hash used to prefix a subscribers collections
+pub(crate) fn account_hash(account_id: &AccountId) -> Vec<u8> {
+    env::sha256(account_id.as_bytes())
+}
+
+/// returns the hash used to prefix the collections of the profile numbered `nonce`,
+/// profiles from the first deployment keep the unsalted `account_hash`
+pub(crate) fn profile_hash(account_id: &AccountId, nonce: u64) -> Vec<u8> {
+    if nonce == 0 {
+        return account_hash(account_id);
+    }
+    let mut salted = account_id.as_bytes().to_vec();
+    salted.extend_from_slice(&nonce.to_le_bytes());
+    env::sha256(&salted)
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
 pub enum ProfileType {
     Creator,
     Consumer
@@ -37,118 +419,738 @@ impl ProfileType {
         match profile_type {
             "creator" => Self::Creator,
             "consumer" => Self::Consumer,
-            _ => panic!("enter a valid profile type")
+            _ => ContractError::InvalidInput("enter a valid profile type".to_owned()).panic()
         }
     }
 }
 
-impl Default for Profile {
-    fn default() -> Self {
-        let mut content: LookupMap<String, String> = LookupMap::new(
-            StorageKeys::Content
-        );
-        content.insert(
-            &"date".to_owned(),
-            &"content test".to_owned()
+impl Profile {
+    pub fn new(account_id: &AccountId, nonce: u64, profile_type: ProfileType, cost: U128, payment_interval: i32) -> Self {
+        let account_hash = profile_hash(account_id, nonce);
+        let content: LookupMap<String, String> = LookupMap::new(
+            StorageKeys::ProfileContent { account_hash: account_hash.clone() }
         );
         let subscribers = LookupMap::new(
-            StorageKeys::Subscribers
+            StorageKeys::ProfileSubscribers { account_hash: account_hash.clone() }
         );
-        let mut costs: LookupMap<String, U128> = LookupMap::new(
-            StorageKeys::Cost
+        let mut costs = LookupMap::<String, U128>::new(
+            StorageKeys::ProfileCost { account_hash: account_hash.clone() }
         );
-        costs.insert(&"cost".to_owned(), &U128::from(10u128.pow(25)));
+        costs.insert(&"cost".to_owned(), &cost);
         let mut content_count: LookupMap<String, i32> = LookupMap::new(
-        StorageKeys::ContentCount
+            StorageKeys::ProfileContentCount { account_hash: account_hash.clone() }
+        );
+        content_count.insert(&"content_count".to_owned(), &0);
+        let bundles = UnorderedMap::new(
+            StorageKeys::ProfileBundles { account_hash: account_hash.clone() }
+        );
+        let expiries = LookupMap::new(
+            StorageKeys::ProfileExpiries { account_hash: account_hash.clone() }
+        );
+        let content_items = LookupMap::new(
+            StorageKeys::ProfileContentItems { account_hash: account_hash.clone() }
+        );
+        let content_ids = Vector::new(
+            StorageKeys::ProfileContentIds { account_hash: account_hash.clone() }
+        );
+        let payments = LookupMap::new(
+            StorageKeys::ProfilePayments { account_hash: account_hash.clone() }
+        );
+        let content_visibility = LookupMap::new(
+            StorageKeys::ProfileContentVisibility { account_hash: account_hash.clone() }
+        );
+        let purchases = LookupSet::new(
+            StorageKeys::ProfilePurchases { account_hash: account_hash.clone() }
+        );
+        let trials = LookupMap::new(
+            StorageKeys::ProfileTrials { account_hash: account_hash.clone() }
+        );
+        let promo_codes = LookupMap::new(
+            StorageKeys::ProfilePromoCodes { account_hash }
         );
-        content_count.insert(&"content_count".to_owned(), &1);
         Self {
-            profile_type: ProfileType::Creator,
+            nonce,
+            profile_type,
             content,
+            content_items,
+            content_ids,
             subscribers,
             costs,
             content_count,
-            payment_interval: 4
+            payment_interval,
+            bundles,
+            access_mode: AccessMode::ContentCount,
+            expiries,
+            subscriber_count: 0,
+            max_post_gap: None,
+            last_published_block: None,
+            payments,
+            payees: Vec::new(),
+            content_visibility,
+            purchases,
+            trial_posts: None,
+            trials,
+            promo_codes
         }
     }
-}
 
-impl Profile {
-    pub fn new(profile_type: ProfileType, cost: U128, payment_interval: i32) -> Self {
-        let content: LookupMap<String, String> = LookupMap::new(
-            StorageKeys::Content
-        );
-        let subscribers = LookupMap::new(
-            StorageKeys::Subscribers
-        );
-        let mut costs = LookupMap::<String, U128>::new(
-            StorageKeys::Cost
+    /// Rebuilds a profile that was created with the legacy shared prefixes
+    /// under prefixes derived from `account_id`
+    ///
+    /// LookupMaps can't be iterated, so the content dates and subscribers to
+    /// carry over must be listed by the caller. The legacy maps are shared by
+    /// every profile, so the cost and content count are read from this
+    /// profile's own view of them
+    pub fn migrate(&self, account_id: &AccountId, dates: Vec<String>, subscribers: Vec<AccountId>) -> Self {
+        if !self.uses_legacy_prefixes() {
+            ContractError::InvalidState("this profile already uses its own storage prefixes".to_owned()).panic();
+        }
+        let cost = match self.costs.get(&"cost".to_owned()) {
+            Some(cost) => cost,
+            None => ContractError::InvalidState("could not access cost".to_owned()).panic()
+        };
+        let mut profile = Self::new(
+            account_id,
+            self.nonce,
+            self.profile_type.clone(),
+            cost,
+            self.payment_interval
         );
-        costs.insert(&"cost".to_owned(), &cost);
-        let mut content_count: LookupMap<String, i32> = LookupMap::new(
-            StorageKeys::ContentCount
+        profile.content_count.insert(&"content_count".to_owned(), &self.current_content_count());
+        for date in dates {
+            if let Some(content) = self.content.get(&date) {
+                profile.content.insert(&date, &content);
+                profile.content_ids.push(&date);
+            }
+        }
+        for subscriber in subscribers {
+            if let Some(count) = self.subscribers.get(&subscriber) {
+                profile.subscribers.insert(&subscriber, &count);
+            }
+        }
+        profile
+    }
+
+    /// Returns whether the profile still reads from the legacy prefixes shared by every profile
+    pub fn uses_legacy_prefixes(&self) -> bool {
+        self.content.try_to_vec().unwrap() == LookupMap::<String, String>::new(StorageKeys::Content).try_to_vec().unwrap()
+            && self.subscribers.try_to_vec().unwrap() == LookupMap::<AccountId, i32>::new(StorageKeys::Subscribers).try_to_vec().unwrap()
+            && self.costs.try_to_vec().unwrap() == LookupMap::<String, U128>::new(StorageKeys::Cost).try_to_vec().unwrap()
+            && self.content_count.try_to_vec().unwrap() == LookupMap::<String, i32>::new(StorageKeys::ContentCount).try_to_vec().unwrap()
+    }
+
+    /// Credits `subscriber_address` with a new subscription
+    ///
+    /// For `AccessMode::ContentCount` this is `credit` pieces of content from the
+    /// current content count. Access is checked against `count + payment_interval`,
+    /// so the stored count is offset by the difference between `credit` and
+    /// `payment_interval`. For `AccessMode::Period` access runs for one period
+    /// from the current block timestamp. Any refundable earlier payment is dropped
+    pub fn subscribe(&mut self, subscriber_address: &AccountId, credit: i32) {
+        if self.subscribers.get(subscriber_address).is_none() && self.expiries.get(subscriber_address).is_none() {
+            self.subscriber_count += 1;
+        }
+        self.payments.remove(subscriber_address);
+        match self.access_mode {
+            AccessMode::ContentCount => {
+                self.subscribers.insert(
+                    subscriber_address,
+                    &(self.current_content_count() + credit - self.payment_interval)
+                );
+            },
+            AccessMode::Period { duration } => {
+                let expires_at = match env::block_timestamp().checked_add(duration) {
+                    Some(expires_at) => expires_at,
+                    None => ContractError::InvalidInput("the subscription period ends too far in the future".to_owned()).panic()
+                };
+                self.expiries.insert(subscriber_address, &expires_at);
+            }
+        }
+    }
+
+    /// Records the payment `subscriber_address` just made, so it can be refunded
+    /// if the creator misses their `max_post_gap`
+    ///
+    /// Only content count subscriptions to creators with a commitment are recorded,
+    /// any earlier record is dropped otherwise
+    pub fn record_payment(&mut self, subscriber_address: &AccountId, amount: Balance, credit: i32) {
+        match (&self.access_mode, &self.max_post_gap) {
+            (AccessMode::ContentCount, Some(max_post_gap)) => {
+                let content_count = self.current_content_count();
+                self.payments.insert(subscriber_address, &Payment {
+                    amount,
+                    credit,
+                    content_count,
+                    paid_at: env::block_timestamp(),
+                    paid_at_block: env::block_height(),
+                    max_post_gap: max_post_gap.clone(),
+                    payees: self.payees.clone()
+                });
+            },
+            _ => {
+                self.payments.remove(subscriber_address);
+            }
+        }
+    }
+
+    /// Block timestamp of the most recent post, `None` before the first one
+    pub fn last_published_at(&self) -> Option<u64> {
+        if self.content_ids.is_empty() {
+            return None;
+        }
+        self.content_ids
+            .get(self.content_ids.len() - 1)
+            .and_then(|id| self.find_content(&id).ok())
+            .map(|content| content.created_at)
+    }
+
+    /// Pieces of content the creator has published
+    pub fn current_content_count(&self) -> i32 {
+        match self.content_count.get(&"content_count".to_owned()) {
+            Some(content_count) => content_count,
+            None => ContractError::InvalidState("could not get content count".to_owned()).panic()
+        }
+    }
+
+    /// Returns the pass for the subscription `subscriber_address` was just credited with
+    pub fn new_pass(&self, creator_address: &AccountId, subscriber_address: &AccountId, credit: i32) -> Pass {
+        let content_count = self.current_content_count();
+        Pass {
+            creator: creator_address.clone(),
+            profile_nonce: self.nonce,
+            content_count,
+            payment_interval: credit,
+            expires_at: match self.access_mode {
+                AccessMode::Period { .. } => self.expiries.get(subscriber_address),
+                AccessMode::ContentCount => None
+            }
+        }
+    }
+
+    /// Returns whether `pass` was minted by this profile rather than one removed before it was added
+    pub fn is_own_pass(&self, pass: &Pass) -> bool {
+        pass.profile_nonce == self.nonce
+    }
+
+    /// Returns whether `pass` still gives access
+    pub fn is_pass_valid(&self, pass: &Pass) -> bool {
+        if !self.is_own_pass(pass) {
+            return false;
+        }
+        match pass.expires_at {
+            Some(expires_at) => env::block_timestamp() < expires_at,
+            None => self.current_content_count() <= pass.content_count + pass.payment_interval
+        }
+    }
+
+    /// Returns whether the current subscription of `subscriber_address` is the one `pass` was minted for
+    pub fn is_pass_subscription(&self, subscriber_address: &AccountId, pass: &Pass) -> bool {
+        if !self.is_own_pass(pass) {
+            return false;
+        }
+        match pass.expires_at {
+            Some(expires_at) => self.expiries.get(subscriber_address) == Some(expires_at),
+            None => self.subscribers.get(subscriber_address)
+                == Some(pass.content_count + pass.payment_interval - self.payment_interval)
+        }
+    }
+
+    /// Ends the access `subscriber_address` has from their last payment and drops its refund record
+    pub fn end_subscription(&mut self, subscriber_address: &AccountId) {
+        self.payments.remove(subscriber_address);
+        if self.subscribers.get(subscriber_address).is_some() {
+            let content_count = self.current_content_count();
+            self.subscribers.insert(
+                subscriber_address,
+                &(content_count - self.payment_interval - 1)
             );
-        content_count.insert(&"content_count".to_owned(), &0);
-        Self {
-            profile_type,
-            content,
-            subscribers,
-            costs,
+        }
+        if self.expiries.get(subscriber_address).is_some() {
+            self.expiries.insert(subscriber_address, &env::block_timestamp());
+        }
+    }
+
+    /// Ends the subscription of `subscriber_address` and returns the unused share
+    /// of their last payment, once the creator has gone longer than they committed
+    /// to without publishing
+    ///
+    /// Returns the amount owed with the payees the payment was split between
+    pub fn refund(&mut self, subscriber_address: &AccountId) -> Result<(Balance, Vec<Payee>), ContractError> {
+        let payment = match self.payments.get(subscriber_address) {
+            Some(payment) => payment,
+            None => return Err(ContractError::NothingToRefund)
+        };
+        let content_count = self.current_content_count();
+        let used = std::cmp::min(std::cmp::max(content_count - payment.content_count, 0), payment.credit);
+        let unused = payment.credit - used;
+        if unused == 0 {
+            return Err(ContractError::NothingToRefund);
+        }
+        let last_activity_at = std::cmp::max(payment.paid_at, self.last_published_at().unwrap_or(0));
+        let last_activity_block = std::cmp::max(payment.paid_at_block, self.last_published_block.unwrap_or(0));
+        if !payment.max_post_gap.has_passed(last_activity_at, last_activity_block) {
+            return Err(ContractError::RefundNotDue);
+        }
+        self.end_subscription(subscriber_address);
+        Ok((payment.amount * unused as Balance / payment.credit as Balance, payment.payees))
+    }
+
+    pub fn set_max_post_gap(&mut self, max_post_gap: Option<PostGap>) {
+        match self.profile_type {
+            ProfileType::Creator => self.max_post_gap = max_post_gap,
+            ProfileType::Consumer => ContractError::NotCreator.panic()
+        }
+    }
+
+    /// Splits future payments between `payees`, an empty list gives the creator everything
+    pub fn set_payees(&mut self, payees: Vec<Payee>) {
+        if let ProfileType::Consumer = self.profile_type {
+            ContractError::NotCreator.panic();
+        }
+        let mut total: u16 = 0;
+        for (index, payee) in payees.iter().enumerate() {
+            if payee.share_bps == 0 {
+                ContractError::InvalidInput(format!("{} must have a share above 0", payee.account_id)).panic();
+            }
+            if payees[..index].iter().any(|other| other.account_id == payee.account_id) {
+                ContractError::InvalidInput(format!("{} is listed more than once", payee.account_id)).panic();
+            }
+            total = total.saturating_add(payee.share_bps);
+        }
+        if !payees.is_empty() && total != TOTAL_SHARE_BPS {
+            ContractError::InvalidInput(format!("shares must add up to {} basis points", TOTAL_SHARE_BPS)).panic();
+        }
+        self.payees = payees;
+    }
+
+    /// Divides `amount` between the payees, the rounding remainder goes to `creator_address`
+    pub fn split_payment(&self, creator_address: &AccountId, amount: Balance) -> Vec<(AccountId, Balance)> {
+        split_between(&self.payees, creator_address, amount)
+    }
+
+    pub fn to_view(&self) -> ProfileView {
+        let cost = match self.costs.get(&"cost".to_owned()) {
+            Some(cost) => cost,
+            None => ContractError::InvalidState("could not access cost".to_owned()).panic()
+        };
+        let content_count = self.current_content_count();
+        ProfileView {
+            profile_type: self.profile_type.clone(),
+            cost,
+            payment_interval: self.payment_interval,
+            access_mode: self.access_mode.clone(),
             content_count,
-            payment_interval
+            subscriber_count: self.subscriber_count
         }
     }
 
-    pub fn subscribe(&mut self) {
-        let subscriber_address = env::signer_account_id();
-        if let Some(content_count) = self.content_count.get(&"content_count".to_owned()) {
-            self.subscribers.insert(&subscriber_address, &content_count);
+    /// Returns whether `subscriber_address` still has access, or `None` if they never subscribed
+    pub fn is_subscription_active(&self, subscriber_address: &AccountId) -> Option<bool> {
+        match self.access_mode {
+            AccessMode::ContentCount => {
+                let content_count = self.current_content_count();
+                self.subscribers
+                    .get(subscriber_address)
+                    .map(|count| content_count <= count + self.payment_interval)
+            },
+            AccessMode::Period { .. } => {
+                self.expiries
+                    .get(subscriber_address)
+                    .map(|expiry| env::block_timestamp() < expiry)
+            }
         }
     }
 
-    pub fn get_content(&self, date: String, is_owner: bool) -> Result<String, String> {
-        if is_owner {
-            match self.content.get(&date) {
-                Some(content) => Ok(content),
-                None => Err("Could not find content for that date".to_owned())
+    /// Returns what is left of the subscription of `subscriber_address`, or `None` if they never subscribed
+    ///
+    /// The remaining credit is `count + payment_interval - content_count`, matching
+    /// `is_subscription_active`
+    pub fn get_subscription(&self, creator_address: &AccountId, subscriber_address: &AccountId) -> Option<SubscriptionView> {
+        match self.access_mode {
+            AccessMode::ContentCount => {
+                let content_count = self.current_content_count();
+                self.subscribers.get(subscriber_address).map(|count| SubscriptionView {
+                    creator: creator_address.clone(),
+                    remaining_credit: Some(count + self.payment_interval - content_count),
+                    expires_at: None
+                })
+            },
+            AccessMode::Period { .. } => {
+                self.expiries.get(subscriber_address).map(|expiry| SubscriptionView {
+                    creator: creator_address.clone(),
+                    remaining_credit: None,
+                    expires_at: Some(expiry)
+                })
             }
-        } else {
-            let subscriber_address = env::signer_account_id();
-            if let Some(content_count) = self.content_count.get(&"content_count".to_owned()) {
-                match self.subscribers.get(&subscriber_address) {
-                    Some(count) => {
-                        if content_count <= count + self.payment_interval {
-                            match self.content.get(&date) {
-                                Some(content) => Ok(content),
-                                None => Err("Could not find content for that date".to_owned())
-                            }
-                        } else {
-                            env::log_str("Please top up as current subscription has ended");
-                            Err("Please top up as current subscription has ended".to_owned())
-                        }
-                    },
-                    None => {
-                        env::log_str("Not a subscriber");
-                        Err("Not a subscriber, please subscribe".to_owned())
-                    }
+        }
+    }
+
+    /// Returns what is left of the access `pass` gives, in the same terms as `get_subscription`
+    pub fn get_pass_subscription(&self, pass: &Pass) -> SubscriptionView {
+        match pass.expires_at {
+            Some(expires_at) => SubscriptionView {
+                creator: pass.creator.clone(),
+                remaining_credit: None,
+                expires_at: Some(expires_at)
+            },
+            None => {
+                let content_count = self.current_content_count();
+                SubscriptionView {
+                    creator: pass.creator.clone(),
+                    remaining_credit: Some(pass.content_count + pass.payment_interval - content_count),
+                    expires_at: None
                 }
-            } else {
-                Err("Could not get content count".to_owned())
             }
         }
     }
 
-    pub fn add_content(&mut self, date: String, content: String) {
+    /// Offers new subscribers `trial_posts` pieces of content for free, `None` stops offering a trial
+    pub fn set_trial_posts(&mut self, trial_posts: Option<i32>) {
         match self.profile_type {
-            ProfileType::Creator => self.content.insert(&date, &content),
-            ProfileType::Consumer => panic!(
-                "{}",
-                "Please create a creator profile to add content".to_owned()
-            )
+            ProfileType::Creator => {
+                if matches!(trial_posts, Some(posts) if posts <= 0) {
+                    ContractError::InvalidInput("a trial must include at least one piece of content".to_owned()).panic();
+                }
+                self.trial_posts = trial_posts;
+            },
+            ProfileType::Consumer => ContractError::NotCreator.panic()
+        }
+    }
+
+    /// Returns whether `subscriber_address` can still start a free trial, which is only offered
+    /// to accounts that have never had a trial or a paid subscription
+    pub fn is_trial_available(&self, subscriber_address: &AccountId) -> bool {
+        self.trial_posts.is_some()
+            && !self.trials.contains_key(subscriber_address)
+            && !self.subscribers.contains_key(subscriber_address)
+            && !self.expiries.contains_key(subscriber_address)
+    }
+
+    /// Starts the one free trial of `subscriber_address`, returning the pieces of content it covers
+    pub fn start_trial(&mut self, subscriber_address: &AccountId) -> i32 {
+        let trial_posts = match self.trial_posts {
+            Some(trial_posts) if self.is_trial_available(subscriber_address) => trial_posts,
+            _ => ContractError::InvalidState("no free trial is available".to_owned()).panic()
         };
+        let content_count = self.current_content_count();
+        self.trials.insert(subscriber_address, &(content_count + trial_posts));
+        trial_posts
+    }
+
+    /// Returns whether the free trial of `subscriber_address` still has access, or `None` if they never had one
+    pub fn is_trial_active(&self, subscriber_address: &AccountId) -> Option<bool> {
+        self.trials.get(subscriber_address).map(|last_content_count| {
+            self.current_content_count() <= last_content_count
+        })
+    }
+
+    /// Adds a discount for the code hashing to `code_hash`
+    pub fn add_promo_code(&mut self, code_hash: Vec<u8>, promo_code: PromoCode) {
+        match self.profile_type {
+            ProfileType::Creator => {
+                if code_hash.len() != 32 {
+                    ContractError::InvalidInput("code_hash must be the 32 byte sha256 hash of the code".to_owned()).panic();
+                }
+                let is_valid_discount = match &promo_code.discount {
+                    Discount::Percent(percent) => (1..=100).contains(percent),
+                    Discount::Fixed(amount) => amount.0 > 0
+                };
+                if !is_valid_discount {
+                    ContractError::InvalidInput("a discount must be between 1 and 100 percent or a positive amount".to_owned()).panic();
+                }
+                if promo_code.max_uses == 0 {
+                    ContractError::InvalidInput("a promo code must allow at least one use".to_owned()).panic();
+                }
+                if promo_code.expires_at_block < env::block_height() {
+                    ContractError::InvalidInput("a promo code can't expire in the past".to_owned()).panic();
+                }
+                if self.promo_codes.contains_key(&code_hash) {
+                    ContractError::InvalidInput("this promo code already exists, remove it first".to_owned()).panic();
+                }
+                self.promo_codes.insert(&code_hash, &promo_code);
+            },
+            ProfileType::Consumer => ContractError::NotCreator.panic()
+        }
     }
 
-    pub fn update_cost(mut self, cost: U128) {
+    pub fn remove_promo_code(&mut self, code_hash: Vec<u8>) {
+        match self.profile_type {
+            ProfileType::Creator => {
+                if self.promo_codes.remove(&code_hash).is_none() {
+                    ContractError::PromoCodeNotFound.panic();
+                }
+            },
+            ProfileType::Consumer => ContractError::NotCreator.panic()
+        }
+    }
+
+    /// Returns `cost` discounted by `code`, panicking if it can't be used
+    pub fn get_promo_price(&self, code: &str, cost: U128) -> U128 {
+        let promo_code = match self.promo_codes.get(&env::sha256(code.as_bytes())) {
+            Some(promo_code) => promo_code,
+            None => ContractError::PromoCodeNotFound.panic()
+        };
+        if env::block_height() > promo_code.expires_at_block {
+            ContractError::PromoCodeExpired.panic();
+        }
+        if promo_code.uses >= promo_code.max_uses {
+            ContractError::PromoCodeUsedUp.panic();
+        }
+        U128::from(promo_code.discount.apply(cost.0))
+    }
+
+    /// Counts a use of `code`, which must have been checked with `get_promo_price`
+    pub fn redeem_promo_code(&mut self, code: &str) {
+        let code_hash = env::sha256(code.as_bytes());
+        let mut promo_code = match self.promo_codes.get(&code_hash) {
+            Some(promo_code) => promo_code,
+            None => ContractError::PromoCodeNotFound.panic()
+        };
+        promo_code.uses += 1;
+        self.promo_codes.insert(&code_hash, &promo_code);
+    }
+
+    /// Panics if `subscriber_address` still has access from their last payment
+    pub fn assert_subscription_ended(&self, subscriber_address: &AccountId) {
+        if self.is_subscription_active(subscriber_address) == Some(true) {
+            let message = match self.access_mode {
+                AccessMode::ContentCount => "User has content left on current subscription",
+                AccessMode::Period { .. } => "User has time left on current subscription"
+            };
+            env::log_str(message);
+            ContractError::SubscriptionActive.panic();
+        }
+    }
+
+    /// Key of the cost in `token_id` within `costs`
+    pub fn token_cost_key(token_id: &AccountId) -> String {
+        format!("ft:{}", token_id)
+    }
+
+    /// Returns the cost of a subscription paid in `token_id`, if the creator accepts it
+    pub fn get_token_cost(&self, token_id: &AccountId) -> Option<U128> {
+        self.costs.get(&Self::token_cost_key(token_id))
+    }
+
+    pub fn set_token_cost(&mut self, token_id: &AccountId, cost: U128) {
+        match self.profile_type {
+            ProfileType::Creator => self.costs.insert(&Self::token_cost_key(token_id), &cost),
+            ProfileType::Consumer => ContractError::NotCreator.panic()
+        };
+    }
+
+    pub fn set_access_mode(&mut self, access_mode: AccessMode) {
+        match self.profile_type {
+            ProfileType::Creator => self.access_mode = access_mode,
+            ProfileType::Consumer => ContractError::NotCreator.panic()
+        }
+    }
+
+    /// Returns the credit and cost of the default tier, or of the bundle for `bundle` pieces of content
+    pub fn get_bundle(&self, bundle: Option<i32>) -> Bundle {
+        match bundle {
+            Some(_) if self.access_mode != AccessMode::ContentCount => ContractError::InvalidInput(
+                "bundles are only available on content count subscriptions".to_owned()
+            ).panic(),
+            Some(content_count) => match self.bundles.get(&content_count) {
+                Some(cost) => Bundle { content_count, cost },
+                None => ContractError::BundleNotFound.panic()
+            },
+            None => match self.costs.get(&"cost".to_owned()) {
+                Some(cost) => Bundle { content_count: self.payment_interval, cost },
+                None => ContractError::InvalidState("could not access cost".to_owned()).panic()
+            }
+        }
+    }
+
+    /// Returns the default tier followed by every bundle
+    pub fn get_bundles(&self) -> Vec<Bundle> {
+        let mut bundles = vec![self.get_bundle(None)];
+        bundles.extend(
+            self.bundles
+                .iter()
+                .map(|(content_count, cost)| Bundle { content_count, cost })
+        );
+        bundles
+    }
+
+    pub fn add_bundle(&mut self, content_count: i32, cost: U128) {
+        match self.profile_type {
+            ProfileType::Creator => {
+                if content_count <= 0 {
+                    ContractError::InvalidInput(
+                        "a bundle must contain at least one piece of content".to_owned()
+                    ).panic();
+                }
+                self.bundles.insert(&content_count, &cost);
+            },
+            ProfileType::Consumer => ContractError::NotCreator.panic()
+        }
+    }
+
+    pub fn remove_bundle(&mut self, content_count: i32) {
+        if self.bundles.remove(&content_count).is_none() {
+            ContractError::BundleNotFound.panic();
+        }
+    }
+
+    /// Returns the content stored under `date`, falling back to legacy `String` content
+    pub fn find_content(&self, date: &str) -> Result<ContentItem, ContractError> {
+        let date = date.to_owned();
+        match self.content_items.get(&date) {
+            Some(content) => Ok(content),
+            None => match self.content.get(&date) {
+                Some(content) => Ok(ContentItem::from_legacy(content)),
+                None => Err(ContractError::ContentNotFound)
+            }
+        }
+    }
+
+    /// Returns the content under `date` to its creator, or to the holder of `passes`
+    ///
+    /// Subscriptions paid for before passes were minted, free trials and content
+    /// bought on its own are checked against the signer. Anyone else gets public content in full
+    /// and the excerpt of previews
+    pub fn get_content(&self, date: String, is_owner: bool, passes: &[Pass]) -> Result<ContentItem, ContractError> {
+        if is_owner || passes.iter().any(|pass| self.is_pass_valid(pass)) {
+            return self.find_content(&date);
+        }
+        let subscriber_address = env::signer_account_id();
+        if self.purchases.contains(&(subscriber_address.clone(), date.clone())) {
+            return self.find_content(&date);
+        }
+        if self.is_trial_active(&subscriber_address) == Some(true) {
+            return self.find_content(&date);
+        }
+        // every subscription since passes were added comes with one, and transferring or
+        // refunding the pass ends this record, so it only still gives access to subscribers
+        // migrated from before passes existed, who have nothing else to show for their payment
+        let (message, error) = match self.is_subscription_active(&subscriber_address) {
+            Some(true) => return self.find_content(&date),
+            Some(false) => ("Please top up as current subscription has ended", ContractError::SubscriptionExhausted),
+            None if !passes.is_empty() || self.trials.contains_key(&subscriber_address) => (
+                "Please top up as current subscription has ended",
+                ContractError::SubscriptionExhausted
+            ),
+            None => ("Not a subscriber", ContractError::NotSubscriber)
+        };
+        match self.get_visibility(&date) {
+            Visibility::Public => self.find_content(&date),
+            Visibility::Preview { excerpt } => {
+                let mut content = self.find_content(&date)?;
+                content.body = excerpt;
+                content.content_hash = None;
+                env::log_str("Showing a preview, subscribe for the full content");
+                Ok(content)
+            },
+            Visibility::SubscribersOnly => {
+                env::log_str(message);
+                Err(error)
+            }
+        }
+    }
+
+    /// Key of the price of the content under `date` within `costs`
+    pub fn content_price_key(date: &str) -> String {
+        format!("content:{}", date)
+    }
+
+    /// Returns the price of the content under `date` bought on its own, if it is for sale
+    pub fn get_content_price(&self, date: &str) -> Option<U128> {
+        self.costs.get(&Self::content_price_key(date))
+    }
+
+    /// Puts the content under `date` up for sale for `price` yoctoNEAR, `None` takes it off sale
+    pub fn set_content_price(&mut self, date: &str, price: Option<U128>) {
+        match self.profile_type {
+            ProfileType::Creator => {
+                if let Err(error) = self.find_content(date) {
+                    error.panic();
+                }
+                match price {
+                    Some(price) => self.costs.insert(&Self::content_price_key(date), &price),
+                    None => self.costs.remove(&Self::content_price_key(date))
+                };
+            },
+            ProfileType::Consumer => ContractError::NotCreator.panic()
+        }
+    }
+
+    /// Gives `buyer` permanent access to the content under `date`
+    pub fn purchase(&mut self, buyer: &AccountId, date: String) {
+        if !self.purchases.insert(&(buyer.clone(), date)) {
+            ContractError::AlreadyPurchased.panic();
+        }
+    }
+
+    pub fn get_visibility(&self, date: &str) -> Visibility {
+        self.content_visibility
+            .get(&date.to_owned())
+            .unwrap_or(Visibility::SubscribersOnly)
+    }
+
+    /// Sets who can read the content under `date`
+    pub fn set_visibility(&mut self, date: String, visibility: Visibility) {
+        match self.profile_type {
+            ProfileType::Creator => {
+                if let Err(error) = self.find_content(&date) {
+                    error.panic();
+                }
+                match visibility {
+                    Visibility::SubscribersOnly => self.content_visibility.remove(&date),
+                    visibility => self.content_visibility.insert(&date, &visibility)
+                };
+            },
+            ProfileType::Consumer => ContractError::NotCreator.panic()
+        }
+    }
+
+    pub fn add_content(&mut self, date: String, content: ContentItem) {
+        match self.profile_type {
+            ProfileType::Creator => {
+                if self.content_items.insert(&date, &content).is_none() && self.content.get(&date).is_none() {
+                    self.content_ids.push(&date);
+                    self.last_published_block = Some(env::block_height());
+                }
+            },
+            ProfileType::Consumer => ContractError::NotCreator.panic()
+        };
+    }
+
+    fn content_metadata(&self, id: String) -> ContentMetadata {
+        let content = match self.find_content(&id) {
+            Ok(content) => content,
+            Err(error) => error.panic()
+        };
+        let visibility = self.get_visibility(&id);
+        ContentMetadata {
+            id,
+            title: content.title,
+            sequence: content.sequence,
+            created_at: content.created_at,
+            visibility
+        }
+    }
+
+    /// Returns up to `limit` pieces of content, oldest first, starting at `from_index`
+    pub fn list_content(&self, from_index: u64, limit: u64) -> Vec<ContentMetadata> {
+        (from_index..std::cmp::min(from_index.saturating_add(limit), self.content_ids.len()))
+            .filter_map(|index| self.content_ids.get(index))
+            .map(|id| self.content_metadata(id))
+            .collect()
+    }
+
+    /// Returns the `n` most recently added pieces of content, newest first
+    pub fn latest_content(&self, n: u64) -> Vec<ContentMetadata> {
+        let len = self.content_ids.len();
+        (len.saturating_sub(n)..len)
+            .rev()
+            .filter_map(|index| self.content_ids.get(index))
+            .map(|id| self.content_metadata(id))
+            .collect()
+    }
+
+    pub fn update_cost(&mut self, cost: U128) {
         self.costs.insert(&"cost".to_owned(), &cost);
     }
-}
+}
\ No newline at end of file
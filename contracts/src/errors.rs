@@ -0,0 +1,131 @@
+use near_sdk::{env, Balance};
+use std::fmt;
+
+/// Every way a contract call can fail
+///
+/// Errors are raised with `env::panic_str` as `"<CODE>: <message>"`, the codes
+/// are stable so frontends and tests can match on them instead of the message
+#[derive(Debug, PartialEq)]
+pub enum ContractError {
+    NotSubscriber,
+    SubscriptionExhausted,
+    SubscriptionActive,
+    ProfileNotFound,
+    ProfileExists,
+    ContentNotFound,
+    BundleNotFound,
+    NotCreator,
+    InsufficientDeposit { required: Balance, attached: Balance },
+    NotRegistered,
+    InsufficientStorageBalance { required: Balance, available: Balance },
+    StorageInUse,
+    TokenNotAccepted,
+    NotOwner,
+    NotAdmin,
+    Paused,
+    InsufficientBalance { required: Balance, available: Balance },
+    NothingToRefund,
+    RefundNotDue,
+    RefundUnavailable { owed: Balance, available: Balance },
+    NotForSale,
+    AlreadyPurchased,
+    PromoCodeNotFound,
+    PromoCodeExpired,
+    PromoCodeUsedUp,
+    InvalidInput(String),
+    InvalidState(String)
+}
+
+impl ContractError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotSubscriber => "NOT_SUBSCRIBER",
+            Self::SubscriptionExhausted => "SUBSCRIPTION_EXHAUSTED",
+            Self::SubscriptionActive => "SUBSCRIPTION_ACTIVE",
+            Self::ProfileNotFound => "PROFILE_NOT_FOUND",
+            Self::ProfileExists => "PROFILE_EXISTS",
+            Self::ContentNotFound => "CONTENT_NOT_FOUND",
+            Self::BundleNotFound => "BUNDLE_NOT_FOUND",
+            Self::NotCreator => "NOT_CREATOR",
+            Self::InsufficientDeposit { .. } => "INSUFFICIENT_DEPOSIT",
+            Self::NotRegistered => "NOT_REGISTERED",
+            Self::InsufficientStorageBalance { .. } => "INSUFFICIENT_STORAGE_BALANCE",
+            Self::StorageInUse => "STORAGE_IN_USE",
+            Self::TokenNotAccepted => "TOKEN_NOT_ACCEPTED",
+            Self::NotOwner => "NOT_OWNER",
+            Self::NotAdmin => "NOT_ADMIN",
+            Self::Paused => "PAUSED",
+            Self::InsufficientBalance { .. } => "INSUFFICIENT_BALANCE",
+            Self::NothingToRefund => "NOTHING_TO_REFUND",
+            Self::RefundNotDue => "REFUND_NOT_DUE",
+            Self::RefundUnavailable { .. } => "REFUND_UNAVAILABLE",
+            Self::NotForSale => "NOT_FOR_SALE",
+            Self::AlreadyPurchased => "ALREADY_PURCHASED",
+            Self::PromoCodeNotFound => "PROMO_CODE_NOT_FOUND",
+            Self::PromoCodeExpired => "PROMO_CODE_EXPIRED",
+            Self::PromoCodeUsedUp => "PROMO_CODE_USED_UP",
+            Self::InvalidInput(_) => "INVALID_INPUT",
+            Self::InvalidState(_) => "INVALID_STATE"
+        }
+    }
+
+    /// Aborts the call with this error
+    pub fn panic(&self) -> ! {
+        env::panic_str(&self.to_string())
+    }
+}
+
+impl fmt::Display for ContractError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: ", self.code())?;
+        match self {
+            Self::NotSubscriber => write!(f, "Not a subscriber, please subscribe"),
+            Self::SubscriptionExhausted => write!(f, "Please top up as current subscription has ended"),
+            Self::SubscriptionActive => write!(f, "User has content or time left on current subscription"),
+            Self::ProfileNotFound => write!(f, "this profile does not exist"),
+            Self::ProfileExists => write!(f, "this account already has a profile"),
+            Self::ContentNotFound => write!(f, "Could not find content for that date"),
+            Self::BundleNotFound => write!(f, "this profile has no bundle of that size"),
+            Self::NotCreator => write!(f, "Please create a creator profile to do this"),
+            Self::InsufficientDeposit { required, attached } => write!(
+                f,
+                "Attached deposit of {} is less than the required {}",
+                attached,
+                required
+            ),
+            Self::NotRegistered => write!(f, "Account is not registered, please call storage_deposit"),
+            Self::InsufficientStorageBalance { required, available } => write!(
+                f,
+                "Insufficient storage balance, {} yoctoNEAR required but only {} yoctoNEAR available",
+                required,
+                available
+            ),
+            Self::StorageInUse => write!(f, "Cannot unregister while storage is in use, call with force to remove the profile"),
+            Self::TokenNotAccepted => write!(f, "This token is not accepted"),
+            Self::NotOwner => write!(f, "Only the contract owner can do this"),
+            Self::NotAdmin => write!(f, "Only the contract owner or an admin can do this"),
+            Self::Paused => write!(f, "The contract is paused, please try again later"),
+            Self::InsufficientBalance { required, available } => write!(
+                f,
+                "Cannot withdraw {} yoctoNEAR, only {} yoctoNEAR is withdrawable",
+                required,
+                available
+            ),
+            Self::NothingToRefund => write!(f, "There is no unused payment to refund"),
+            Self::RefundNotDue => write!(f, "The creator has published within their committed gap between posts"),
+            Self::RefundUnavailable { owed, available } => write!(
+                f,
+                "Only {} of the {} yoctoNEAR refund is left unwithdrawn, the subscription is kept",
+                available,
+                owed
+            ),
+            Self::NotForSale => write!(f, "This content can't be bought on its own, please subscribe"),
+            Self::AlreadyPurchased => write!(f, "You have already bought this content"),
+            Self::PromoCodeNotFound => write!(f, "This promo code does not exist"),
+            Self::PromoCodeExpired => write!(f, "This promo code has expired"),
+            Self::PromoCodeUsedUp => write!(f, "This promo code has been used the maximum number of times"),
+            Self::InvalidInput(message) => write!(f, "{}", message),
+            Self::InvalidState(message) => write!(f, "{}", message)
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use near_sdk::{
+    env,
+    json_types::U128,
+    serde::Serialize,
+    serde_json,
+    AccountId
+};
+use crate::data_structures::ProfileType;
+
+/// Name and version of the NEP-297 standard these events follow
+const EVENT_STANDARD: &str = "content_subscription";
+const EVENT_STANDARD_VERSION: &str = "1.0.0";
+
+/// State changes logged as NEP-297 `EVENT_JSON:` logs for indexers
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde", tag = "event", content = "data", rename_all = "snake_case")]
+pub enum Event {
+    ProfileCreated(Vec<ProfileCreatedData>),
+    ContentAdded(Vec<ContentAddedData>),
+    Subscribed(Vec<SubscribedData>),
+    CostUpdated(Vec<CostUpdatedData>),
+    Withdrawn(Vec<WithdrawnData>),
+    Refunded(Vec<RefundedData>),
+    SubscriptionGifted(Vec<SubscriptionGiftedData>),
+    FeesWithdrawn(Vec<FeesWithdrawnData>),
+    ContentPurchased(Vec<ContentPurchasedData>),
+    TrialStarted(Vec<TrialStartedData>)
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ProfileCreatedData {
+    pub creator: AccountId,
+    pub profile_type: ProfileType,
+    pub cost: U128,
+    pub payment_interval: i32
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ContentAddedData {
+    pub creator: AccountId,
+    pub content_id: String,
+    pub sequence: i32
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SubscribedData {
+    pub creator: AccountId,
+    pub subscriber: AccountId,
+    pub amount: U128,
+    /// the NEP-141 token paid in, `None` for NEAR
+    pub token_id: Option<AccountId>,
+    /// pieces of content the payment was for
+    pub content_count: i32
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CostUpdatedData {
+    pub creator: AccountId,
+    pub cost: U128,
+    /// the NEP-141 token the cost is in, `None` for NEAR
+    pub token_id: Option<AccountId>,
+    /// pieces of content the cost is for
+    pub content_count: i32
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct WithdrawnData {
+    pub creator: AccountId,
+    /// paid out of the creators balance, in yoctoNEAR or the tokens smallest unit
+    pub amount: U128,
+    /// the NEP-141 token withdrawn, `None` for NEAR
+    pub token_id: Option<AccountId>
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RefundedData {
+    pub creator: AccountId,
+    pub subscriber: AccountId,
+    /// yoctoNEAR returned from the creators balance
+    pub amount: U128
+}
+
+/// Logged alongside `Subscribed` when the subscription was paid for by someone else
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SubscriptionGiftedData {
+    pub creator: AccountId,
+    pub payer: AccountId,
+    pub beneficiary: AccountId,
+    pub amount: U128,
+    /// the NEP-141 token paid in, `None` for NEAR
+    pub token_id: Option<AccountId>,
+    pub message: Option<String>
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FeesWithdrawnData {
+    /// account the fees were sent to
+    pub receiver: AccountId,
+    /// paid out of the platform fees, in yoctoNEAR or the tokens smallest unit
+    pub amount: U128,
+    /// the NEP-141 token withdrawn, `None` for NEAR
+    pub token_id: Option<AccountId>
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ContentPurchasedData {
+    pub creator: AccountId,
+    pub buyer: AccountId,
+    pub content_id: String,
+    pub amount: U128
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TrialStartedData {
+    pub creator: AccountId,
+    pub subscriber: AccountId,
+    /// pieces of content the trial is for
+    pub content_count: i32
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event: &'a Event
+}
+
+impl Event {
+    pub fn emit(&self) {
+        let log = EventLog {
+            standard: EVENT_STANDARD,
+            version: EVENT_STANDARD_VERSION,
+            event: self
+        };
+        env::log_str(&format!(
+            "EVENT_JSON:{}",
+            serde_json::to_string(&log).unwrap()
+        ));
+    }
+}
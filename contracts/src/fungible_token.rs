@@ -0,0 +1,288 @@
+use crate::*;
+use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+use near_sdk::{
+    assert_one_yocto,
+    serde::Deserialize,
+    serde_json::{self, json},
+    Gas,
+    PromiseOrValue,
+    PromiseResult
+};
+
+const GAS_FOR_FT_TRANSFER: Gas = Gas(10_000_000_000_000);
+const GAS_FOR_ON_WITHDRAW_TOKEN: Gas = Gas(10_000_000_000_000);
+
+/// The `msg` expected with `ft_transfer_call`, e.g. `{"creator_address": "creator.near"}`
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+struct SubscribeMessage {
+    creator_address: AccountId,
+    /// subscribes this account instead of the sender, who still pays
+    #[serde(default)]
+    beneficiary: Option<AccountId>
+}
+
+impl Contract {
+    /// Credits each account its share of a payment in `token_id` split by `Profile::split_payment`
+    pub(crate) fn internal_credit_token_split(&mut self, token_id: &AccountId, split: Vec<(AccountId, Balance)>) {
+        for (account_id, amount) in split {
+            let key = (account_id, token_id.clone());
+            let mut token_balance = self.token_balances.get(&key).unwrap_or_default();
+            token_balance.balance += amount;
+            self.token_balances.insert(&key, &token_balance);
+        }
+    }
+
+    /// Holds the platform fee on a payment of `amount` in `token_id` under the contracts
+    /// own account, returning the fee
+    fn internal_collect_token_fee(&mut self, token_id: &AccountId, amount: Balance) -> Balance {
+        let fee = amount * self.platform_fee_bps as Balance / TOTAL_SHARE_BPS as Balance;
+        if fee > 0 {
+            self.internal_credit_token_split(token_id, vec![(env::current_account_id(), fee)]);
+        }
+        fee
+    }
+
+    /// Holds `amount` of the `token_id` balance of `account_id` as pending and sends
+    /// it to `receiver_id`, the caller adds the callback that resolves it
+    fn internal_send_token(&mut self, account_id: AccountId, token_id: &AccountId, amount: U128, receiver_id: &AccountId) -> Promise {
+        let key = (account_id, token_id.clone());
+        let mut token_balance = self.token_balances.get(&key).unwrap_or_default();
+        if amount.0 == 0 || amount.0 > token_balance.withdrawable() {
+            ContractError::InsufficientBalance {
+                required: amount.0,
+                available: token_balance.withdrawable()
+            }.panic();
+        }
+        token_balance.pending += amount.0;
+        self.token_balances.insert(&key, &token_balance);
+        Promise::new(token_id.clone()).function_call(
+            "ft_transfer".to_owned(),
+            json!({
+                "receiver_id": receiver_id,
+                "amount": amount
+            }).to_string().into_bytes(),
+            1,
+            GAS_FOR_FT_TRANSFER
+        )
+    }
+
+    /// Takes a token withdrawal out of the balance of `account_id` if its `ft_transfer`
+    /// succeeded, otherwise makes it withdrawable again, returning whether it succeeded
+    fn internal_resolve_token_withdrawal(&mut self, account_id: AccountId, token_id: AccountId, amount: U128) -> bool {
+        let key = (account_id, token_id);
+        let mut token_balance = match self.token_balances.get(&key) {
+            Some(token_balance) => token_balance,
+            None => ContractError::InvalidState("withdrawal without a token balance".to_owned()).panic()
+        };
+        token_balance.pending -= amount.0;
+        let transferred = matches!(env::promise_result(0), PromiseResult::Successful(_));
+        if transferred {
+            token_balance.balance -= amount.0;
+        }
+        self.token_balances.insert(&key, &token_balance);
+        transferred
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Allows creators to price subscriptions in the NEP-141 token `token_id`,
+    /// only the owner can call this
+    pub fn add_accepted_token(&mut self, token_id: AccountId) {
+        self.assert_owner();
+        self.accepted_tokens.insert(&token_id);
+    }
+
+    pub fn remove_accepted_token(&mut self, token_id: AccountId) {
+        self.assert_owner();
+        self.accepted_tokens.remove(&token_id);
+    }
+
+    pub fn get_accepted_tokens(&self) -> Vec<AccountId> {
+        self.accepted_tokens.to_vec()
+    }
+
+    /// Sets the callers subscription cost in `token_id`, in the tokens smallest unit
+    pub fn set_token_cost(&mut self, token_id: AccountId, cost: U128) {
+        if !self.accepted_tokens.contains(&token_id) {
+            ContractError::TokenNotAccepted.panic();
+        }
+        let payment_interval = self.internal_update_own_profile(|profile| {
+            profile.set_token_cost(&token_id, cost);
+            profile.payment_interval
+        });
+        Event::CostUpdated(vec![CostUpdatedData {
+            creator: env::predecessor_account_id(),
+            cost,
+            token_id: Some(token_id),
+            content_count: payment_interval
+        }]).emit();
+    }
+
+    pub fn get_token_cost(&self, creator_address: AccountId, token_id: AccountId) -> Option<U128> {
+        match self.internal_get_profile(&creator_address) {
+            Some(profile) => profile.get_token_cost(&token_id),
+            None => ContractError::ProfileNotFound.panic()
+        }
+    }
+
+    /// Payments in `token_id` that `account_id` can withdraw now, in the tokens smallest unit
+    pub fn withdrawable_token_balance(&self, account_id: AccountId, token_id: AccountId) -> U128 {
+        U128::from(
+            self.token_balances
+                .get(&(account_id, token_id))
+                .map(|token_balance| token_balance.withdrawable())
+                .unwrap_or(0)
+        )
+    }
+
+    /// Sends `amount` of the callers payments in `token_id` to them with `ft_transfer`,
+    /// so they must be registered with the token
+    ///
+    /// Held as pending until the transfer resolves, like a NEAR `withdraw`
+    #[payable]
+    pub fn withdraw_token(&mut self, token_id: AccountId, amount: U128) -> Promise {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        self.internal_send_token(account_id.clone(), &token_id, amount, &account_id)
+            .then(Promise::new(env::current_account_id()).function_call(
+                "on_withdraw_token".to_owned(),
+                json!({
+                    "account_id": account_id,
+                    "token_id": token_id,
+                    "amount": amount
+                }).to_string().into_bytes(),
+                0,
+                GAS_FOR_ON_WITHDRAW_TOKEN
+            ))
+    }
+
+    /// Takes a token withdrawal out of the balance if its `ft_transfer` succeeded,
+    /// otherwise makes it withdrawable again
+    #[private]
+    pub fn on_withdraw_token(&mut self, account_id: AccountId, token_id: AccountId, amount: U128) -> bool {
+        let transferred = self.internal_resolve_token_withdrawal(account_id.clone(), token_id.clone(), amount);
+        if transferred {
+            Event::Withdrawn(vec![WithdrawnData {
+                creator: account_id,
+                amount,
+                token_id: Some(token_id)
+            }]).emit();
+        }
+        transferred
+    }
+
+    /// Platform fees collected in `token_id` that the owner can withdraw now
+    pub fn get_token_fees(&self, token_id: AccountId) -> U128 {
+        self.withdrawable_token_balance(env::current_account_id(), token_id)
+    }
+
+    /// Sends `amount` of the platform fees collected in `token_id` to `receiver_id`,
+    /// the owner when omitted, only the owner can call this
+    ///
+    /// Held as pending until the transfer resolves, like `withdraw_fees`, and the
+    /// receiver must be registered with the token
+    #[payable]
+    pub fn withdraw_token_fees(&mut self, token_id: AccountId, amount: U128, receiver_id: Option<AccountId>) -> Promise {
+        assert_one_yocto();
+        self.assert_owner();
+        let receiver_id = receiver_id.unwrap_or_else(|| self.owner_id.clone());
+        self.internal_send_token(env::current_account_id(), &token_id, amount, &receiver_id)
+            .then(Promise::new(env::current_account_id()).function_call(
+                "on_withdraw_token_fees".to_owned(),
+                json!({
+                    "receiver_id": receiver_id,
+                    "token_id": token_id,
+                    "amount": amount
+                }).to_string().into_bytes(),
+                0,
+                GAS_FOR_ON_WITHDRAW_TOKEN
+            ))
+    }
+
+    /// Takes a fee withdrawal out of the fees held in `token_id` if its `ft_transfer`
+    /// succeeded, otherwise makes it withdrawable again
+    #[private]
+    pub fn on_withdraw_token_fees(&mut self, receiver_id: AccountId, token_id: AccountId, amount: U128) -> bool {
+        let transferred = self.internal_resolve_token_withdrawal(env::current_account_id(), token_id.clone(), amount);
+        if transferred {
+            Event::FeesWithdrawn(vec![FeesWithdrawnData {
+                receiver: receiver_id,
+                amount,
+                token_id: Some(token_id)
+            }]).emit();
+        }
+        transferred
+    }
+}
+
+#[near_bindgen]
+impl FungibleTokenReceiver for Contract {
+    /// Subscribes `sender_id`, or the `beneficiary` in `msg`, to the creator named in `msg`, paid in the calling token
+    ///
+    /// The creators cost, less the platform fee, is held for them, or split between their
+    /// payees, until they `withdraw_token` it. Anything above the cost is returned as unused
+    /// and refunded by the token contract, any panic refunds the full amount
+    ///
+    /// Token payments are final, no refund record is kept for `claim_refund` and one
+    /// left by an earlier NEAR payment is dropped
+    fn ft_on_transfer(&mut self, sender_id: AccountId, amount: U128, msg: String) -> PromiseOrValue<U128> {
+        self.assert_not_paused();
+        let token_id = env::predecessor_account_id();
+        if !self.accepted_tokens.contains(&token_id) {
+            ContractError::TokenNotAccepted.panic();
+        }
+        let message: SubscribeMessage = match serde_json::from_str(&msg) {
+            Ok(message) => message,
+            Err(_) => ContractError::InvalidInput(
+                "msg must name the creator, e.g. {\"creator_address\": \"creator.near\"}".to_owned()
+            ).panic()
+        };
+        let mut profile = match self.internal_get_profile(&message.creator_address) {
+            Some(profile) => profile,
+            None => ContractError::ProfileNotFound.panic()
+        };
+        let cost = match profile.get_token_cost(&token_id) {
+            Some(cost) => cost,
+            None => ContractError::TokenNotAccepted.panic()
+        };
+        if amount.0 < cost.0 {
+            ContractError::InsufficientDeposit {
+                required: cost.0,
+                attached: amount.0
+            }.panic();
+        }
+        let beneficiary = message.beneficiary.unwrap_or_else(|| sender_id.clone());
+        profile.assert_subscription_ended(&beneficiary);
+        let payment_interval = profile.payment_interval;
+        let initial_storage_usage = env::storage_usage();
+        profile.subscribe(&beneficiary, payment_interval);
+        let pass = profile.new_pass(&message.creator_address, &beneficiary, payment_interval);
+        let creator_amount = cost.0 - self.internal_collect_token_fee(&token_id, cost.0);
+        let split = profile.split_payment(&message.creator_address, creator_amount);
+        self.internal_set_profile(&message.creator_address, profile);
+        self.internal_credit_token_split(&token_id, split);
+        self.internal_add_subscription(&beneficiary, &message.creator_address);
+        self.internal_mint_pass(&beneficiary, pass);
+        self.charge_storage(&sender_id, initial_storage_usage);
+        Event::Subscribed(vec![SubscribedData {
+            creator: message.creator_address.clone(),
+            subscriber: beneficiary.clone(),
+            amount: cost,
+            token_id: Some(token_id.clone()),
+            content_count: payment_interval
+        }]).emit();
+        if beneficiary != sender_id {
+            Event::SubscriptionGifted(vec![SubscriptionGiftedData {
+                creator: message.creator_address,
+                payer: sender_id,
+                beneficiary,
+                amount: cost,
+                token_id: Some(token_id),
+                message: None
+            }]).emit();
+        }
+        PromiseOrValue::Value(U128::from(amount.0 - cost.0))
+    }
+}
@@ -1,173 +1,660 @@
 mod data_structures;
 use data_structures::*;
+mod errors;
+use errors::ContractError;
+mod storage_management;
+mod fungible_token;
+mod events;
+use events::*;
+mod upgrade;
+use upgrade::{write_state_version, VersionedProfile};
+mod roles;
+mod revenue;
+mod passes;
+mod purchases;
+mod trials;
+mod promo_codes;
+mod subscriptions;
+use near_contract_standards::non_fungible_token::{NonFungibleToken, TokenId};
 use near_sdk::{
     borsh::{self, BorshDeserialize, BorshSerialize},
     env,
-    collections::{LookupMap},
+    collections::{LookupMap, UnorderedSet},
     AccountId,
+    Balance,
     json_types::U128,
     Promise,
-    near_bindgen
+    near_bindgen,
+    PanicOnDefault
 };
 
 #[near_bindgen]
-#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct Contract {
-    data: LookupMap<AccountId, Profile>,
+    data: LookupMap<AccountId, VersionedProfile>,
+    storage_accounts: LookupMap<AccountId, StorageAccount>,
+    /// NEP-141 tokens creators can price subscriptions in
+    accepted_tokens: UnorderedSet<AccountId>,
+    /// can upgrade the contract and manage admins
+    owner_id: AccountId,
+    /// can pause and unpause the contract alongside the owner
+    admins: UnorderedSet<AccountId>,
+    /// blocks new profiles, content and subscriptions while set
+    paused: bool,
+    /// the only profile this contract hosts when it was deployed by the factory
+    creator_id: Option<AccountId>,
+    /// subscription payments held for each creator until they withdraw them
+    creator_balances: LookupMap<AccountId, CreatorBalance>,
+    /// NEP-171 tokens minted for each subscription, holding one gives its access
+    passes: NonFungibleToken,
+    /// the subscription each pass was minted for, removed once it is refunded
+    pass_terms: LookupMap<TokenId, Pass>,
+    /// basis points of each NEAR subscription payment kept by the platform
+    platform_fee_bps: u16,
+    /// platform fees held until the owner withdraws them
+    treasury: CreatorBalance,
+    /// yoctoNEAR of platform fees collected since the fee was introduced
+    fees_collected: Balance,
+    /// creators each account has subscribed to, the reverse of each profiles `subscribers`
+    subscriptions: LookupMap<AccountId, UnorderedSet<AccountId>>,
+    /// token payments held for each account and NEP-141 token until they are withdrawn
+    token_balances: LookupMap<(AccountId, AccountId), CreatorBalance>,
+    /// profiles added so far, the nonce of the last one
+    profiles_created: u64,
+    /// refund records of passes sent with `nft_transfer_call`, held until the receiver keeps or returns them
+    held_payments: LookupMap<TokenId, Payment>
 }
 
-impl Default for Contract {
-    fn default() -> Self {
-        let mut data = LookupMap::new(StorageKeys::Data);
-        data.insert(
-            &"bob_near".parse().unwrap(),
-            &Profile::default()
+/// Parses `value` supplied as the `name` argument, panicking with `ContractError::InvalidInput`
+fn parse_input<T: std::str::FromStr>(value: &str, name: &str) -> T {
+    value
+        .parse::<T>()
+        .unwrap_or_else(|_| ContractError::InvalidInput(format!("{} is not a valid {}", value, name)).panic())
+}
+
+/// Parses a whole number of NEAR into yoctoNEAR
+fn parse_near(cost: &str) -> U128 {
+    match parse_input::<u128>(cost, "cost").checked_mul(1_000_000_000_000_000_000_000_000) {
+        Some(cost_in_yocto_near) => U128::from(cost_in_yocto_near),
+        None => ContractError::InvalidInput(format!("{} NEAR is too large", cost)).panic()
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_get_profile(&self, account_id: &AccountId) -> Option<Profile> {
+        match self.data.get(account_id) {
+            Some(profile) => Some(profile.into()),
+            None => self.internal_get_legacy_profile(account_id)
+        }
+    }
+
+    /// Credits `beneficiary` with a subscription to `creator_address` paid for by the
+    /// attached deposit, returning the cost taken from it
+    ///
+    /// The signer pays for the storage used and the predecessor is refunded any overpayment
+    fn internal_subscribe(
+        &mut self,
+        creator_address: AccountId,
+        beneficiary: AccountId,
+        bundle: Option<String>,
+        promo_code: Option<String>
+    ) -> U128 {
+        self.assert_not_paused();
+        let mut profile = match self.internal_get_profile(&creator_address) {
+            Some(profile) => profile,
+            None => ContractError::ProfileNotFound.panic()
+        };
+        let bundle = profile.get_bundle(
+            bundle.map(|bundle| parse_input::<i32>(&bundle, "bundle"))
         );
-        Self {
-            data
+        let amount = match &promo_code {
+            Some(code) => profile.get_promo_price(code, bundle.cost),
+            None => bundle.cost
+        };
+        let deposit = env::attached_deposit();
+        if deposit < amount.0 {
+            ContractError::InsufficientDeposit {
+                required: amount.0,
+                attached: deposit
+            }.panic();
+        }
+        profile.assert_subscription_ended(&beneficiary);
+        let initial_storage_usage = env::storage_usage();
+        if let Some(code) = &promo_code {
+            profile.redeem_promo_code(code);
+        }
+        let creator_amount = amount.0 - self.internal_collect_fee(amount.0);
+        profile.subscribe(&beneficiary, bundle.content_count);
+        profile.record_payment(&beneficiary, creator_amount, bundle.content_count);
+        let pass = profile.new_pass(&creator_address, &beneficiary, bundle.content_count);
+        let split = profile.split_payment(&creator_address, creator_amount);
+        self.internal_set_profile(&creator_address, profile);
+        self.internal_add_subscription(&beneficiary, &creator_address);
+        self.internal_credit_split(split);
+        self.internal_mint_pass(&beneficiary, pass);
+        self.charge_storage(&env::signer_account_id(), initial_storage_usage);
+        Event::Subscribed(vec![SubscribedData {
+            creator: creator_address,
+            subscriber: beneficiary,
+            amount,
+            token_id: None,
+            content_count: bundle.content_count
+        }]).emit();
+        let refund = deposit - amount.0;
+        if refund > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
         }
+        amount
+    }
+
+    pub(crate) fn internal_set_profile(&mut self, account_id: &AccountId, profile: Profile) {
+        self.data.insert(account_id, &VersionedProfile::V1(profile));
+    }
+
+    /// Applies `update` to the callers profile and saves it, charging the caller for
+    /// any storage it takes up. Returns what `update` returns
+    ///
+    /// The caller is the predecessor rather than the signer, so a contract the
+    /// creator calls can't change their profile on their behalf
+    pub(crate) fn internal_update_own_profile<T>(&mut self, update: impl FnOnce(&mut Profile) -> T) -> T {
+        let account_id = env::predecessor_account_id();
+        let mut profile = match self.internal_get_profile(&account_id) {
+            Some(profile) => profile,
+            None => ContractError::ProfileNotFound.panic()
+        };
+        let initial_storage_usage = env::storage_usage();
+        let result = update(&mut profile);
+        self.internal_set_profile(&account_id, profile);
+        self.charge_storage(&account_id, initial_storage_usage);
+        result
+    }
+
+    /// Numbers a new profile, so its collections don't share prefixes with a removed one
+    pub(crate) fn internal_next_profile_nonce(&mut self) -> u64 {
+        self.profiles_created += 1;
+        self.profiles_created
+    }
+
+    /// Removes the profile of `account_id`, its collection entries stay in storage
+    /// under prefixes no later profile uses
+    pub(crate) fn internal_remove_profile(&mut self, account_id: &AccountId) {
+        self.data.remove(account_id);
+        self.internal_remove_legacy_profile(account_id);
     }
 }
 
 #[near_bindgen]
 impl Contract {
-    #[result_serializer(borsh)]
-    pub fn get_profile(&mut self, account_id: &AccountId) ->  Option<Profile> {
-        self.data.get(&account_id)
+    #[init]
+    pub fn new(owner_id: AccountId) -> Self {
+        write_state_version();
+        Self {
+            data: LookupMap::new(StorageKeys::Profiles),
+            storage_accounts: LookupMap::new(StorageKeys::StorageAccounts),
+            accepted_tokens: UnorderedSet::new(StorageKeys::AcceptedTokens),
+            owner_id,
+            admins: UnorderedSet::new(StorageKeys::Admins),
+            paused: false,
+            creator_id: None,
+            creator_balances: LookupMap::new(StorageKeys::CreatorBalances),
+            passes: NonFungibleToken::new(
+                StorageKeys::Passes,
+                env::current_account_id(),
+                Some(StorageKeys::PassMetadata),
+                Some(StorageKeys::PassesPerOwner),
+                None::<StorageKeys>
+            ),
+            pass_terms: LookupMap::new(StorageKeys::PassTerms),
+            platform_fee_bps: 0,
+            treasury: CreatorBalance::default(),
+            fees_collected: 0,
+            subscriptions: LookupMap::new(StorageKeys::Subscriptions),
+            token_balances: LookupMap::new(StorageKeys::TokenBalances),
+            profiles_created: 0,
+            held_payments: LookupMap::new(StorageKeys::HeldPayments)
+        }
     }
 
-    pub fn add_profile(&mut self, account_id: AccountId, profile_type: String, cost: String, payment_interval: String) {
-        let cost_in_yocto_near = U128::from(
-            cost.parse::<u128>().unwrap() * 1_000_000_000_000_000_000_000_000
+    /// Sets up a contract that only hosts the creator profile of `creator_id`,
+    /// as deployed to a sub-account by the factory
+    ///
+    /// The account balance pays for the creators storage, subscribers still register with `storage_deposit`
+    #[init]
+    pub fn new_creator_contract(owner_id: AccountId, creator_id: AccountId, cost: String, payment_interval: String) -> Self {
+        let cost_in_yocto_near = parse_near(&cost);
+        let payment_interval = parse_input::<i32>(&payment_interval, "payment_interval");
+        let mut contract = Self {
+            creator_id: Some(creator_id.clone()),
+            ..Self::new(owner_id)
+        };
+        let nonce = contract.internal_next_profile_nonce();
+        contract.internal_set_profile(
+            &creator_id,
+            Profile::new(
+                &creator_id,
+                nonce,
+                ProfileType::Creator,
+                cost_in_yocto_near,
+                payment_interval
+            )
         );
-        let payment_interval = payment_interval.parse::<i32>().unwrap();
-        self.data.insert(
+        Event::ProfileCreated(vec![ProfileCreatedData {
+            creator: creator_id,
+            profile_type: ProfileType::Creator,
+            cost: cost_in_yocto_near,
+            payment_interval
+        }]).emit();
+        contract
+    }
+
+    pub fn get_creator_id(&self) -> Option<AccountId> {
+        self.creator_id.clone()
+    }
+
+    pub fn get_profile(&self, account_id: AccountId) -> Option<ProfileView> {
+        self.internal_get_profile(&account_id).map(|profile| profile.to_view())
+    }
+
+    /// Creates the profile of `account_id`, which has to be the caller and can't already have one
+    pub fn add_profile(&mut self, account_id: AccountId, profile_type: String, cost: String, payment_interval: String) {
+        self.assert_not_paused();
+        if let Some(creator_id) = &self.creator_id {
+            ContractError::InvalidState(format!("this contract only hosts the profile of {}", creator_id)).panic();
+        }
+        if account_id != env::predecessor_account_id() {
+            ContractError::InvalidInput("a profile can only be added for the calling account".to_owned()).panic();
+        }
+        if self.internal_get_profile(&account_id).is_some() {
+            ContractError::ProfileExists.panic();
+        }
+        let cost_in_yocto_near = parse_near(&cost);
+        let payment_interval = parse_input::<i32>(&payment_interval, "payment_interval");
+        let profile_type = ProfileType::new(&profile_type);
+        let initial_storage_usage = env::storage_usage();
+        let nonce = self.internal_next_profile_nonce();
+        self.internal_set_profile(
             &account_id,
-            &Profile::new(
-                ProfileType::new(&profile_type),
+            Profile::new(
+                &account_id,
+                nonce,
+                profile_type.clone(),
                 cost_in_yocto_near,
                 payment_interval
             )
         );
+        self.charge_storage(&account_id, initial_storage_usage);
+        Event::ProfileCreated(vec![ProfileCreatedData {
+            creator: account_id,
+            profile_type,
+            cost: cost_in_yocto_near,
+            payment_interval
+        }]).emit();
     }
 
-    pub fn subscribe(&mut self, creator_address: AccountId) {
-        let mut profile = match self.get_profile(&creator_address) {
+    /// Moves the callers profile from the legacy shared storage prefixes to
+    /// prefixes derived from their account id
+    ///
+    /// `dates` and `subscribers` list the content and subscriber entries to carry over.
+    /// Fails once the profile has been migrated, running it again would reset it
+    pub fn migrate_profile(&mut self, dates: Vec<String>, subscribers: Vec<AccountId>) {
+        let account_id = env::predecessor_account_id();
+        let profile = match self.internal_get_profile(&account_id) {
             Some(profile) => profile,
-            None => return
+            None => ContractError::ProfileNotFound.panic()
         };
-        let amount = match profile.costs.get(&"cost".to_owned()) {
-            Some(cost) => cost,
-            None => panic!("could not access cost")
-        };
-        if let Some(content_count) = profile.content_count.get(&"content_count".to_owned()) {
-            match profile.subscribers.get(&env::signer_account_id()) {
-                Some(count) => {
-                    if content_count > count + profile.payment_interval {
-                        Promise::new(creator_address).transfer(amount.0);
-                        profile.subscribe();
-                    } else {
-                        env::log_str("User has content left on current subscription");
-                        panic!("User has content left on current subscription");
-                    }
-                },
-                None => {
-                    Promise::new(creator_address).transfer(amount.0);
-                    profile.subscribe();
-                }
-            }
+        self.internal_remove_legacy_profile(&account_id);
+        let initial_storage_usage = env::storage_usage();
+        self.internal_set_profile(
+            &account_id,
+            profile.migrate(&account_id, dates, subscribers)
+        );
+        self.charge_storage(&account_id, initial_storage_usage);
+    }
+
+    /// Subscribes the signer to `creator_address`, paid for by the attached deposit
+    ///
+    /// `bundle` picks one of the creators bundles by its number of pieces of
+    /// content, the default cost and `payment_interval` are used when omitted.
+    /// The deposit must cover the cost, anything above it is refunded. The cost
+    /// is held in the creators balance until they `withdraw` it
+    ///
+    /// `promo_code` takes one of the creators discounts off the cost and counts
+    /// as one of its uses
+    ///
+    /// Without a deposit, bundle or promo code this starts the creators free trial
+    /// instead, if they offer one and the signer hasn't had it yet
+    #[payable]
+    pub fn subscribe(&mut self, creator_address: AccountId, bundle: Option<String>, promo_code: Option<String>) {
+        let subscriber_address = env::signer_account_id();
+        if env::attached_deposit() == 0
+            && bundle.is_none()
+            && promo_code.is_none()
+            && self.internal_is_trial_available(&creator_address, &subscriber_address)
+        {
+            self.internal_start_trial(creator_address, subscriber_address);
+        } else {
+            self.internal_subscribe(creator_address, subscriber_address, bundle, promo_code);
+        }
+    }
+
+    /// Subscribes `beneficiary` to `creator_address`, paid for by the signers attached deposit
+    ///
+    /// Works like `subscribe`, but the access is credited to `beneficiary`. The
+    /// signer pays for the storage and gets any overpayment back
+    #[payable]
+    pub fn subscribe_for(&mut self, creator_address: AccountId, beneficiary: AccountId, bundle: Option<String>) {
+        let amount = self.internal_subscribe(creator_address.clone(), beneficiary.clone(), bundle, None);
+        let payer = env::signer_account_id();
+        if payer != beneficiary {
+            Event::SubscriptionGifted(vec![SubscriptionGiftedData {
+                creator: creator_address,
+                payer,
+                beneficiary,
+                amount,
+                token_id: None,
+                message: None
+            }]).emit();
         }
     }
 
-    pub fn add_content(&mut self, date: String, content: String) {
-        let creator_address = env::signer_account_id();
-        let mut profile = match self.get_profile(&creator_address) {
+    /// Gives `beneficiary` a subscription to `creator_address` paid for by the signer,
+    /// with an optional `message` for them that is logged in the gift event
+    #[payable]
+    pub fn gift_subscription(&mut self, creator_address: AccountId, beneficiary: AccountId, bundle: Option<String>, message: Option<String>) {
+        let payer = env::signer_account_id();
+        if payer == beneficiary {
+            ContractError::InvalidInput("a subscription can't be gifted to yourself, call subscribe".to_owned()).panic();
+        }
+        let amount = self.internal_subscribe(creator_address.clone(), beneficiary.clone(), bundle, None);
+        Event::SubscriptionGifted(vec![SubscriptionGiftedData {
+            creator: creator_address,
+            payer,
+            beneficiary,
+            amount,
+            token_id: None,
+            message
+        }]).emit();
+    }
+
+    /// Adds `content` to the callers profile under `date`, stamped with the
+    /// current block timestamp and the profiles new content count
+    ///
+    /// Only subscribers can read it unless `content.visibility` says otherwise.
+    /// Replacing content already under `date` keeps its sequence, timestamp and,
+    /// when `content.visibility` is omitted, visibility, so an edit doesn't use up
+    /// a piece of each subscribers credit
+    pub fn add_content(&mut self, date: String, content: NewContent) {
+        self.assert_not_paused();
+        let creator_address = env::predecessor_account_id();
+        let mut profile = match self.internal_get_profile(&creator_address) {
             Some(profile) => profile,
             None => return
         };
-        if let Some(current_content_count) = profile.content_count.get(&"content_count".to_owned()) {
-            profile.content_count.insert(&"content_count".to_owned(), &(current_content_count + 1));
-        }
-        profile.add_content(date, content);
+        let initial_storage_usage = env::storage_usage();
+        let visibility = content.visibility.clone().unwrap_or_else(|| profile.get_visibility(&date));
+        let item = match profile.find_content(&date) {
+            Ok(existing) => ContentItem {
+                created_at: existing.created_at,
+                ..ContentItem::new(content, existing.sequence)
+            },
+            Err(_) => {
+                let sequence = profile.current_content_count() + 1;
+                profile.content_count.insert(&"content_count".to_owned(), &sequence);
+                ContentItem::new(content, sequence)
+            }
+        };
+        let sequence = item.sequence;
+        profile.add_content(date.clone(), item);
+        profile.set_visibility(date.clone(), visibility);
+        self.internal_set_profile(&creator_address, profile);
+        self.charge_storage(&creator_address, initial_storage_usage);
+        Event::ContentAdded(vec![ContentAddedData {
+            creator: creator_address,
+            content_id: date,
+            sequence
+        }]).emit();
+    }
+
+    /// Changes who can read the callers content under `date`
+    pub fn set_content_visibility(&mut self, date: String, visibility: Visibility) {
+        self.internal_update_own_profile(|profile| profile.set_visibility(date, visibility));
     }
 
-    pub fn get_content(&mut self, creator_address: AccountId, date: String) -> String {
-        let profile = match self.get_profile(&creator_address) {
+    pub fn get_content(&mut self, creator_address: AccountId, date: String) -> ContentItem {
+        let profile = match self.internal_get_profile(&creator_address) {
             Some(profile) => profile,
-            None => panic!("this profile does not exist")
+            None => ContractError::ProfileNotFound.panic()
         };
-        let is_owner = env::signer_account_id() == creator_address;
+        let signer = env::signer_account_id();
+        let is_owner = signer == creator_address;
+        let passes: Vec<Pass> = self.internal_get_passes(&signer, &creator_address)
+            .into_iter()
+            .map(|(_, pass)| pass)
+            .collect();
         match profile.get_content(
             date,
-            is_owner
+            is_owner,
+            &passes
         ) {
             Ok(content) => content,
-            Err(error) => panic!("{}", error)
+            Err(error) => error.panic()
         }
     }
 
-    pub fn get_cost(mut self) -> String {
+    /// Lists the content of `creator_address` in the order it was added, without the gated body
+    ///
+    /// Starts at `from_index` (default 0) and returns at most `limit` (default 10) items
+    pub fn list_content(&self, creator_address: AccountId, from_index: Option<u64>, limit: Option<u64>) -> Vec<ContentMetadata> {
+        match self.internal_get_profile(&creator_address) {
+            Some(profile) => profile.list_content(
+                from_index.unwrap_or(0),
+                limit.unwrap_or(10)
+            ),
+            None => ContractError::ProfileNotFound.panic()
+        }
+    }
+
+    /// Returns the `n` most recent pieces of content of `creator_address`, newest first
+    pub fn latest_content(&self, creator_address: AccountId, n: u64) -> Vec<ContentMetadata> {
+        match self.internal_get_profile(&creator_address) {
+            Some(profile) => profile.latest_content(n),
+            None => ContractError::ProfileNotFound.panic()
+        }
+    }
+
+    pub fn get_cost(&self) -> String {
         let account_id = env::signer_account_id();
-        let profile = match self.get_profile(&account_id) {
+        let profile = match self.internal_get_profile(&account_id) {
             Some(profile) => profile,
-            None => panic!("this profile does not exist")
+            None => ContractError::ProfileNotFound.panic()
         };
         let cost = match profile.costs.get(&"cost".to_owned()) {
             Some(cost) => cost,
-            None => panic!("could not access cost")
+            None => ContractError::InvalidState("could not access cost".to_owned()).panic()
         };
         format!("{}", cost.0 / 1_000_000_000_000_000_000_000_000)
     }
 
     pub fn update_cost(&mut self, cost: String) {
-        let account_id = env::signer_account_id();
-        let profile = match self.get_profile(&account_id) {
-            Some(profile) => profile,
-            None => panic!("this profile does not exist")
-        };
-        let cost_in_yocto_near = U128::from(
-            cost.parse::<u128>().unwrap() * 1_000_000_000_000_000_000_000_000
+        let cost_in_yocto_near = parse_near(&cost);
+        let payment_interval = self.internal_update_own_profile(|profile| {
+            profile.update_cost(cost_in_yocto_near);
+            profile.payment_interval
+        });
+        Event::CostUpdated(vec![CostUpdatedData {
+            creator: env::predecessor_account_id(),
+            cost: cost_in_yocto_near,
+            token_id: None,
+            content_count: payment_interval
+        }]).emit();
+    }
+
+    /// Adds a bundle of `content_count` pieces of content for `cost` NEAR to the callers profile,
+    /// replacing any bundle of the same size
+    pub fn add_bundle(&mut self, content_count: String, cost: String) {
+        let cost_in_yocto_near = parse_near(&cost);
+        let content_count = parse_input::<i32>(&content_count, "content_count");
+        self.internal_update_own_profile(|profile| profile.add_bundle(content_count, cost_in_yocto_near));
+        Event::CostUpdated(vec![CostUpdatedData {
+            creator: env::predecessor_account_id(),
+            cost: cost_in_yocto_near,
+            token_id: None,
+            content_count
+        }]).emit();
+    }
+
+    pub fn remove_bundle(&mut self, content_count: String) {
+        let content_count = parse_input::<i32>(&content_count, "content_count");
+        self.internal_update_own_profile(|profile| profile.remove_bundle(content_count));
+    }
+
+    /// Switches the callers profile between `"content_count"` access, where a payment covers
+    /// `payment_interval` pieces of content, and `"period"` access, where a payment covers `period_days` days
+    ///
+    /// Existing subscriptions are tracked per mode, so they don't carry over when switching
+    pub fn set_access_mode(&mut self, access_mode: String, period_days: Option<String>) {
+        let access_mode = AccessMode::new(
+            &access_mode,
+            period_days.map(|days| parse_input::<u64>(&days, "period_days"))
         );
-        profile.update_cost(cost_in_yocto_near);
+        self.internal_update_own_profile(|profile| profile.set_access_mode(access_mode));
+    }
+
+    /// Lists the pricing tiers of `creator_address`, starting with the default one
+    pub fn get_bundles(&self, creator_address: AccountId) -> Vec<Bundle> {
+        match self.internal_get_profile(&creator_address) {
+            Some(profile) => profile.get_bundles(),
+            None => ContractError::ProfileNotFound.panic()
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use near_sdk::test_utils::{VMContextBuilder};
-    use near_sdk::{testing_env, VMContext};
+    use storage_management::STORAGE_REGISTRATION_BYTES;
+    use near_contract_standards::storage_management::StorageManagement;
+    use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+    use near_sdk::test_utils::{get_created_receipts, get_logs, VMContextBuilder};
+    use near_sdk::{testing_env, PromiseOrValue, PromiseResult, RuntimeFeesConfig, VMConfig, VMContext};
+    use near_sdk::collections::{LookupSet, UnorderedMap, Vector};
+    use near_sdk::json_types::Base64VecU8;
+    use near_contract_standards::non_fungible_token::core::{NonFungibleTokenCore, NonFungibleTokenResolver};
+    use near_contract_standards::non_fungible_token::enumeration::NonFungibleTokenEnumeration;
+    use near_contract_standards::non_fungible_token::metadata::{NonFungibleTokenMetadataProvider, NFT_METADATA_SPEC};
 
     fn get_context(is_view: bool, signer_address: AccountId, signer_balance: u128) -> VMContext {
         VMContextBuilder::new()
-            .signer_account_id(signer_address)
+            .signer_account_id(signer_address.clone())
+            .predecessor_account_id(signer_address)
             .account_balance(signer_balance)
             .is_view(is_view)
             .build()
     }
 
+    /// Context of a call `account_id` signs and makes directly, the builder
+    /// methods add a deposit, block timestamp or anything else the call needs
+    fn get_call_context(account_id: &str) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .signer_account_id(account_id.parse().unwrap())
+            .predecessor_account_id(account_id.parse().unwrap())
+            .account_balance(10u128.pow(26));
+        builder
+    }
+
+    /// asserts `result` is a panic raised with the code of `error`
+    fn assert_contract_error<T>(result: std::thread::Result<T>, error: ContractError) {
+        let message = match result {
+            Ok(_) => panic!("expected {} but the call succeeded", error.code()),
+            Err(payload) => match payload.downcast::<String>() {
+                Ok(message) => *message,
+                Err(_) => panic!("expected {} but the panic had no message", error.code())
+            }
+        };
+        assert!(
+            message.contains(error.code()),
+            "expected {} but got {}",
+            error.code(),
+            message
+        );
+    }
+
+    fn text_content(body: &str) -> NewContent {
+        NewContent {
+            title: "test".to_owned(),
+            body: body.to_owned(),
+            mime_type: "text/plain".to_owned(),
+            content_hash: None,
+            visibility: None
+        }
+    }
+
+    /// returns a contract owned by `owner.near` with a `bob_near` creator profile,
+    /// which charges 10 NEAR for 4 pieces of content and has posted once under `date`
+    fn new_contract() -> Contract {
+        let mut contract = Contract::new("owner.near".parse().unwrap());
+        let account_id = "bob_near".parse().unwrap();
+        let mut profile = Profile::new(
+            &account_id,
+            contract.internal_next_profile_nonce(),
+            ProfileType::Creator,
+            U128::from(10u128.pow(25)),
+            4
+        );
+        profile.add_content("date".to_owned(), ContentItem::new(text_content("content test"), 1));
+        profile.content_count.insert(&"content_count".to_owned(), &1);
+        contract.internal_set_profile(&account_id, profile);
+        contract
+    }
+
+    /// returns `new_contract` with each of `account_ids` holding 1 NEAR of storage balance
+    fn registered_contract(account_ids: &[&str]) -> Contract {
+        let mut contract = new_contract();
+        for account_id in account_ids {
+            contract.storage_accounts.insert(
+                &account_id.parse().unwrap(),
+                &StorageAccount {
+                    deposit: 10u128.pow(24),
+                    bytes_used: STORAGE_REGISTRATION_BYTES
+                }
+            );
+        }
+        contract
+    }
+
     #[test]
     fn test_add_profile() {
         let context = get_context(
             false,
-            "consumer".parse().unwrap(),
+            "dan.testnet".parse().unwrap(),
             10u128.pow(20)
         );
         testing_env!(context);
-        let account_id = "dan.testnet".parse().unwrap();
-        let mut contract = Contract::default();
+        let account_id: AccountId = "dan.testnet".parse().unwrap();
+        let mut contract = registered_contract(&["dan.testnet"]);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.add_profile("eve.testnet".parse().unwrap(), "consumer".to_owned(), "1".to_owned(), "4".to_owned());
+        }));
+        assert_contract_error(result, ContractError::InvalidInput(String::new()));
         contract.add_profile(
-            account_id,
+            account_id.clone(),
             "consumer".to_owned(),
             "1".to_owned(),
             "4".to_owned()
         );
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.add_profile(account_id.clone(), "creator".to_owned(), "1".to_owned(), "4".to_owned());
+        }));
+        assert_contract_error(result, ContractError::ProfileExists);
         let test_profile = Profile::new(
+            &"dan.testnet".parse().unwrap(),
+            1,
             ProfileType::Consumer,
             U128::from(10u128.pow(20)),
             4
         );
-        let profile = match contract.get_profile(&"dan.testnet".parse().unwrap()) {
+        let profile = match contract.internal_get_profile(&"dan.testnet".parse().unwrap()) {
             Some(profile) => profile,
             None => panic!()
         };
@@ -183,15 +670,11 @@ mod tests {
 
     #[test]
     fn test_subscribe() {
-        let context = get_context(
-            false,
-            "dan.testnet".parse().unwrap(),
-            10u128.pow(25)
-        );
+        let context = get_call_context("dan.testnet").attached_deposit(10u128.pow(25)).build();
         testing_env!(context);
-        let mut contract = Contract::default();
-        contract.subscribe("bob_near".parse().unwrap());
-        let profile = match contract.get_profile(&"bob_near".parse().unwrap()) {
+        let mut contract = registered_contract(&["dan.testnet"]);
+        contract.subscribe("bob_near".parse().unwrap(), None, None);
+        let profile = match contract.internal_get_profile(&"bob_near".parse().unwrap()) {
             Some(profile) => profile,
             None => return
         };
@@ -199,25 +682,47 @@ mod tests {
             Some(1),
             profile.subscribers.get(&"dan.testnet".parse().unwrap())
         );
+        assert!(get_created_receipts().is_empty());
+        assert_eq!(
+            U128::from(10u128.pow(25)),
+            contract.withdrawable_balance("bob_near".parse().unwrap())
+        );
     }
 
     #[test]
-    fn test_subscribe_low_balance() {
-        let context = get_context(
-            false,
-            "dan.testnet".parse().unwrap(),
-            10u128.pow(24)
+    fn test_subscribe_overpayment_refunded() {
+        let context = get_call_context("dan.testnet").attached_deposit(3 * 10u128.pow(25)).build();
+        testing_env!(context);
+        let mut contract = registered_contract(&["dan.testnet"]);
+        contract.subscribe("bob_near".parse().unwrap(), None, None);
+        let profile = match contract.internal_get_profile(&"bob_near".parse().unwrap()) {
+            Some(profile) => profile,
+            None => panic!()
+        };
+        assert_eq!(
+            Some(1),
+            profile.subscribers.get(&"dan.testnet".parse().unwrap())
+        );
+        let receipts = get_created_receipts();
+        assert_eq!(1, receipts.len());
+        assert_eq!("dan.testnet", receipts[0].receiver_id.as_str());
+        assert_eq!(
+            U128::from(10u128.pow(25)),
+            contract.withdrawable_balance("bob_near".parse().unwrap())
         );
+    }
+
+    #[test]
+    fn test_subscribe_low_balance() {
+        let context = get_call_context("dan.testnet").attached_deposit(10u128.pow(24)).build();
         testing_env!(context);
         let result = std::panic::catch_unwind(|| 
             {
-                let mut contract = Contract::default();
-                contract.subscribe("bob_near".parse().unwrap());
+                let mut contract = new_contract();
+                contract.subscribe("bob_near".parse().unwrap(), None, None);
             }
         );
-        assert!(
-            result.is_err()
-        );
+        assert_contract_error(result, ContractError::InsufficientDeposit { required: 0, attached: 0 });
     }
 
     #[test]
@@ -230,34 +735,28 @@ mod tests {
         testing_env!(context);
         let result = std::panic::catch_unwind(||
             {
-                let mut contract = Contract::default();
+                let mut contract = new_contract();
                 contract.get_content(
                     "bob_near".parse().unwrap(),
                     "date".to_owned()
                 )
             }
         );
-        assert!(
-            result.is_err()
-        );
+        assert_contract_error(result, ContractError::NotSubscriber);
     }
 
     #[test]
     fn test_get_content_subscriber() {
-        let context = get_context(
-            false,
-            "dan_testnet".parse().unwrap(),
-            10u128.pow(25)
-        );
+        let context = get_call_context("dan_testnet").attached_deposit(10u128.pow(25)).build();
         testing_env!(context);
-        let mut contract = Contract::default();
-        contract.subscribe("bob_near".parse().unwrap());
+        let mut contract = registered_contract(&["dan_testnet"]);
+        contract.subscribe("bob_near".parse().unwrap(), None, None);
         assert_eq!(
             "content test".to_owned(),
             contract.get_content(
                 "bob_near".parse().unwrap(),
                 "date".to_owned()
-            )
+            ).body
         );
     }
 
@@ -269,14 +768,14 @@ mod tests {
             10u128.pow(25)
         );
         testing_env!(context);
-        let mut contract = Contract::default();
-        contract.add_content("date part 2".to_owned(), "content test part 2".to_owned());
+        let mut contract = registered_contract(&["bob_near"]);
+        contract.add_content("date part 2".to_owned(), text_content("content test part 2"));
         assert_eq!(
             "content test part 2".to_owned(),
             contract.get_content(
                 "bob_near".parse().unwrap(),
                 "date part 2".to_owned()
-            )
+            ).body
         );
     }
 
@@ -290,7 +789,7 @@ mod tests {
         testing_env!(context);
         let result = std::panic::catch_unwind(|| 
             {
-                let mut contract = Contract::default();
+                let mut contract = registered_contract(&["consumer"]);
                 contract.add_profile(
                     "consumer".parse().unwrap(),
                     "consumer".to_owned(),
@@ -299,13 +798,11 @@ mod tests {
                 );
                 contract.add_content(
                     "date part 2".to_owned(),
-                    "content test part 2".to_owned()
+                    text_content("content test part 2")
                 );
             }
         );
-        assert!(
-            result.is_err()
-        );
+        assert_contract_error(result, ContractError::NotCreator);
     }
 
     #[test]
@@ -317,7 +814,7 @@ mod tests {
         );
         testing_env!(context);
 
-        let mut contract = Contract::default();
+        let mut contract = registered_contract(&["creator"]);
         contract.add_profile(
             "creator".parse().unwrap(),
             "creator".to_owned(),
@@ -332,4 +829,1916 @@ mod tests {
             "2".to_owned()
         );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_profiles_do_not_share_storage() {
+        testing_env!(get_context(false, "consumer".parse().unwrap(), 10u128.pow(25)));
+        let mut contract = registered_contract(&["creator_one", "creator_two", "consumer"]);
+        for creator in ["creator_one", "creator_two"] {
+            testing_env!(get_context(false, creator.parse().unwrap(), 10u128.pow(25)));
+            contract.add_profile(
+                creator.parse().unwrap(),
+                "creator".to_owned(),
+                if creator == "creator_one" { "1".to_owned() } else { "2".to_owned() },
+                "4".to_owned()
+            );
+            contract.add_content("date".to_owned(), text_content(&format!("content from {}", creator)));
+        }
+        testing_env!(get_call_context("consumer").attached_deposit(10u128.pow(24)).build());
+        contract.subscribe("creator_one".parse().unwrap(), None, None);
+
+        let creator_one = contract.internal_get_profile(&"creator_one".parse().unwrap()).unwrap();
+        let creator_two = contract.internal_get_profile(&"creator_two".parse().unwrap()).unwrap();
+        assert_eq!(
+            Some("content from creator_one".to_owned()),
+            creator_one.content_items.get(&"date".to_owned()).map(|content| content.body)
+        );
+        assert_eq!(
+            Some("content from creator_two".to_owned()),
+            creator_two.content_items.get(&"date".to_owned()).map(|content| content.body)
+        );
+        assert_eq!(Some(U128::from(10u128.pow(24))), creator_one.costs.get(&"cost".to_owned()));
+        assert_eq!(Some(U128::from(2 * 10u128.pow(24))), creator_two.costs.get(&"cost".to_owned()));
+        assert_eq!(Some(1), creator_one.subscribers.get(&"consumer".parse().unwrap()));
+        assert_eq!(None, creator_two.subscribers.get(&"consumer".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_migrate_profile() {
+        let context = get_context(
+            false,
+            "creator".parse().unwrap(),
+            10u128.pow(25)
+        );
+        testing_env!(context);
+        let mut contract = registered_contract(&["creator"]);
+        let mut content = LookupMap::new(StorageKeys::Content);
+        content.insert(&"date".to_owned(), &"legacy content".to_owned());
+        let mut subscribers = LookupMap::new(StorageKeys::Subscribers);
+        subscribers.insert(&"consumer".parse().unwrap(), &0);
+        let mut costs = LookupMap::new(StorageKeys::Cost);
+        costs.insert(&"cost".to_owned(), &U128::from(10u128.pow(24)));
+        let mut content_count = LookupMap::new(StorageKeys::ContentCount);
+        content_count.insert(&"content_count".to_owned(), &1);
+        contract.internal_set_profile(
+            &"creator".parse().unwrap(),
+            Profile {
+                nonce: 0,
+                profile_type: ProfileType::Creator,
+                payment_interval: 4,
+                content_count,
+                content,
+                content_items: LookupMap::new(StorageKeys::ProfileContentItems { account_hash: vec![] }),
+                content_ids: Vector::new(StorageKeys::ProfileContentIds { account_hash: vec![] }),
+                subscribers,
+                costs,
+                bundles: UnorderedMap::new(StorageKeys::ProfileBundles { account_hash: vec![] }),
+                access_mode: AccessMode::ContentCount,
+                expiries: LookupMap::new(StorageKeys::ProfileExpiries { account_hash: vec![] }),
+                subscriber_count: 0,
+                max_post_gap: None,
+                last_published_block: None,
+                payments: LookupMap::new(StorageKeys::ProfilePayments { account_hash: vec![] }),
+                payees: Vec::new(),
+                content_visibility: LookupMap::new(StorageKeys::ProfileContentVisibility { account_hash: vec![] }),
+                purchases: LookupSet::new(StorageKeys::ProfilePurchases { account_hash: vec![] }),
+                trial_posts: None,
+                trials: LookupMap::new(StorageKeys::ProfileTrials { account_hash: vec![] }),
+                promo_codes: LookupMap::new(StorageKeys::ProfilePromoCodes { account_hash: vec![] })
+            }
+        );
+        contract.migrate_profile(
+            vec!["date".to_owned()],
+            vec!["consumer".parse().unwrap()]
+        );
+
+        // clear the legacy maps to prove the migrated profile no longer reads them
+        LookupMap::<String, String>::new(StorageKeys::Content).remove(&"date".to_owned());
+        LookupMap::<AccountId, i32>::new(StorageKeys::Subscribers).remove(&"consumer".parse().unwrap());
+        let profile = contract.internal_get_profile(&"creator".parse().unwrap()).unwrap();
+        assert_eq!(Some("legacy content".to_owned()), profile.content.get(&"date".to_owned()));
+        assert_eq!(Some(0), profile.subscribers.get(&"consumer".parse().unwrap()));
+        assert_eq!(Some(1), profile.content_count.get(&"content_count".to_owned()));
+        assert_eq!(Some(U128::from(10u128.pow(24))), profile.costs.get(&"cost".to_owned()));
+
+        // a second migration would recreate the profile under the same prefixes and wipe it
+        contract.add_content("second date".to_owned(), text_content("new content"));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.migrate_profile(vec!["date".to_owned()], vec![]);
+        }));
+        assert_contract_error(result, ContractError::InvalidState(String::new()));
+        let profile = contract.internal_get_profile(&"creator".parse().unwrap()).unwrap();
+        assert_eq!(Some(2), profile.content_count.get(&"content_count".to_owned()));
+        assert_eq!(2, contract.list_content("creator".parse().unwrap(), None, None).len());
+    }
+
+    #[test]
+    fn test_storage_deposit() {
+        let context = get_call_context("consumer").attached_deposit(10u128.pow(24)).build();
+        testing_env!(context);
+        let mut contract = new_contract();
+        assert!(contract.storage_balance_of("consumer".parse().unwrap()).is_none());
+        contract.storage_deposit(None, None);
+        let balance = match contract.storage_balance_of("consumer".parse().unwrap()) {
+            Some(balance) => balance,
+            None => panic!()
+        };
+        let minimum = contract.storage_balance_bounds().min.0;
+        assert_eq!(10u128.pow(24), balance.total.0);
+        assert_eq!(10u128.pow(24) - minimum, balance.available.0);
+
+        contract.storage_deposit(None, Some(true));
+        assert_eq!(
+            10u128.pow(24),
+            contract.storage_balance_of("consumer".parse().unwrap()).unwrap().total.0
+        );
+    }
+
+    #[test]
+    fn test_add_profile_charges_storage() {
+        let context = get_context(
+            false,
+            "creator".parse().unwrap(),
+            10u128.pow(25)
+        );
+        testing_env!(context);
+        let mut contract = registered_contract(&["creator"]);
+        let available = contract.storage_balance_of("creator".parse().unwrap()).unwrap().available.0;
+        let initial_storage_usage = env::storage_usage();
+        contract.add_profile(
+            "creator".parse().unwrap(),
+            "creator".to_owned(),
+            "1".to_owned(),
+            "4".to_owned()
+        );
+        // the storage record itself is rewritten at the same size, so every new byte is the profile's
+        let bytes_used = env::storage_usage() - initial_storage_usage;
+        assert!(bytes_used > 0);
+        assert_eq!(
+            available - u128::from(bytes_used) * env::storage_byte_cost(),
+            contract.storage_balance_of("creator".parse().unwrap()).unwrap().available.0
+        );
+    }
+
+    #[test]
+    fn test_add_profile_unregistered() {
+        let context = get_context(
+            false,
+            "creator".parse().unwrap(),
+            10u128.pow(25)
+        );
+        testing_env!(context);
+        let result = std::panic::catch_unwind(||
+            {
+                let mut contract = new_contract();
+                contract.add_profile(
+                    "creator".parse().unwrap(),
+                    "creator".to_owned(),
+                    "1".to_owned(),
+                    "4".to_owned()
+                );
+            }
+        );
+        assert_contract_error(result, ContractError::NotRegistered);
+    }
+
+    #[test]
+    fn test_storage_unregister_with_profile() {
+        let context = get_call_context("creator").attached_deposit(1).build();
+        testing_env!(context);
+        let mut contract = registered_contract(&["creator"]);
+        contract.add_profile(
+            "creator".parse().unwrap(),
+            "creator".to_owned(),
+            "1".to_owned(),
+            "4".to_owned()
+        );
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(||
+            {
+                contract.storage_unregister(None)
+            }
+        ));
+        assert_contract_error(result, ContractError::StorageInUse);
+        assert!(contract.storage_unregister(Some(true)));
+        assert!(contract.storage_balance_of("creator".parse().unwrap()).is_none());
+        assert!(contract.internal_get_profile(&"creator".parse().unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_profile_added_after_unregister_starts_empty() {
+        testing_env!(get_call_context("creator").attached_deposit(1).build());
+        let mut contract = registered_contract(&["creator", "dan.testnet"]);
+        contract.add_profile(
+            "creator".parse().unwrap(),
+            "creator".to_owned(),
+            "1".to_owned(),
+            "4".to_owned()
+        );
+        contract.add_content("date".to_owned(), text_content("old content"));
+        testing_env!(get_call_context("dan.testnet").attached_deposit(10u128.pow(24)).build());
+        contract.subscribe("creator".parse().unwrap(), None, None);
+        testing_env!(get_call_context("creator").attached_deposit(1).build());
+        assert!(contract.storage_unregister(Some(true)));
+        testing_env!(get_call_context("creator").attached_deposit(10u128.pow(24)).build());
+        contract.storage_deposit(None, None);
+        contract.add_profile(
+            "creator".parse().unwrap(),
+            "creator".to_owned(),
+            "1".to_owned(),
+            "4".to_owned()
+        );
+        let profile = contract.internal_get_profile(&"creator".parse().unwrap()).unwrap();
+        assert_eq!(0, profile.current_content_count());
+        assert_eq!(None, profile.subscribers.get(&"dan.testnet".parse().unwrap()));
+        assert_eq!(None, profile.content_items.get(&"date".to_owned()));
+        assert_eq!(0, profile.content_ids.len());
+        let passes = contract.internal_get_passes(&"dan.testnet".parse().unwrap(), &"creator".parse().unwrap());
+        assert_eq!(1, passes.len());
+        assert!(!profile.is_pass_valid(&passes[0].1));
+        assert!(contract.get_subscriptions("dan.testnet".parse().unwrap(), None, None).is_empty());
+    }
+
+    #[test]
+    fn test_bundles() {
+        let context = get_context(
+            false,
+            "creator".parse().unwrap(),
+            10u128.pow(25)
+        );
+        testing_env!(context);
+        let mut contract = registered_contract(&["creator", "consumer"]);
+        contract.add_profile(
+            "creator".parse().unwrap(),
+            "creator".to_owned(),
+            "1".to_owned(),
+            "3".to_owned()
+        );
+        contract.add_bundle("12".to_owned(), "3".to_owned());
+        assert_eq!(
+            vec![
+                Bundle { content_count: 3, cost: U128::from(10u128.pow(24)) },
+                Bundle { content_count: 12, cost: U128::from(3 * 10u128.pow(24)) }
+            ],
+            contract.get_bundles("creator".parse().unwrap())
+        );
+
+        testing_env!(get_call_context("consumer").attached_deposit(3 * 10u128.pow(24)).build());
+        contract.subscribe("creator".parse().unwrap(), Some("12".to_owned()), None);
+
+        testing_env!(get_context(false, "creator".parse().unwrap(), 10u128.pow(25)));
+        for post in 1..=12 {
+            contract.add_content(format!("{}", post), text_content(&format!("content {}", post)));
+        }
+        testing_env!(get_context(false, "consumer".parse().unwrap(), 10u128.pow(25)));
+        assert_eq!(
+            "content 12".to_owned(),
+            contract.get_content("creator".parse().unwrap(), "12".to_owned()).body
+        );
+
+        testing_env!(get_context(false, "creator".parse().unwrap(), 10u128.pow(25)));
+        contract.add_content("13".to_owned(), text_content("content 13"));
+        testing_env!(get_context(false, "consumer".parse().unwrap(), 10u128.pow(25)));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(||
+            {
+                contract.get_content("creator".parse().unwrap(), "13".to_owned())
+            }
+        ));
+        assert_contract_error(result, ContractError::SubscriptionExhausted);
+    }
+
+    #[test]
+    fn test_subscribe_unknown_bundle() {
+        let context = get_call_context("dan.testnet").attached_deposit(10u128.pow(25)).build();
+        testing_env!(context);
+        let result = std::panic::catch_unwind(||
+            {
+                let mut contract = registered_contract(&["dan.testnet"]);
+                contract.subscribe("bob_near".parse().unwrap(), Some("12".to_owned()), None);
+            }
+        );
+        assert_contract_error(result, ContractError::BundleNotFound);
+    }
+
+    #[test]
+    fn test_period_subscription() {
+        let day = 24 * 60 * 60 * 1_000_000_000;
+        let context = get_context(
+            false,
+            "creator".parse().unwrap(),
+            10u128.pow(25)
+        );
+        testing_env!(context);
+        let mut contract = registered_contract(&["creator", "consumer"]);
+        contract.add_profile(
+            "creator".parse().unwrap(),
+            "creator".to_owned(),
+            "1".to_owned(),
+            "3".to_owned()
+        );
+        contract.set_access_mode("period".to_owned(), Some("30".to_owned()));
+        contract.add_content("date".to_owned(), text_content("content test"));
+
+        let mut context = get_call_context("consumer").attached_deposit(10u128.pow(24)).build();
+        context.block_timestamp = day;
+        testing_env!(context.clone());
+        contract.subscribe("creator".parse().unwrap(), None, None);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(||
+            {
+                contract.subscribe("creator".parse().unwrap(), None, None);
+            }
+        ));
+        assert_contract_error(result, ContractError::SubscriptionActive);
+
+        // posting more content than payment_interval doesn't end a period subscription
+        testing_env!(get_context(false, "creator".parse().unwrap(), 10u128.pow(25)));
+        for post in 1..=4 {
+            contract.add_content(format!("{}", post), text_content(&format!("content {}", post)));
+        }
+        context.attached_deposit = 0;
+        context.block_timestamp = 30 * day;
+        testing_env!(context.clone());
+        assert_eq!(
+            "content 4".to_owned(),
+            contract.get_content("creator".parse().unwrap(), "4".to_owned()).body
+        );
+
+        context.block_timestamp = 31 * day;
+        testing_env!(context.clone());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(||
+            {
+                contract.get_content("creator".parse().unwrap(), "4".to_owned())
+            }
+        ));
+        assert_contract_error(result, ContractError::SubscriptionExhausted);
+
+        context.attached_deposit = 10u128.pow(24);
+        testing_env!(context);
+        contract.subscribe("creator".parse().unwrap(), None, None);
+        assert_eq!(
+            "content 4".to_owned(),
+            contract.get_content("creator".parse().unwrap(), "4".to_owned()).body
+        );
+    }
+
+    #[test]
+    fn test_period_overflow() {
+        testing_env!(get_call_context("bob_near").build());
+        let mut contract = registered_contract(&["bob_near", "dan.testnet"]);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.set_access_mode("period".to_owned(), Some(u64::MAX.to_string()));
+        }));
+        assert_contract_error(result, ContractError::InvalidInput(String::new()));
+
+        // the longest period that fits can't start once the block time is far enough along
+        contract.set_access_mode("period".to_owned(), Some("213503".to_owned()));
+        testing_env!(get_call_context("dan.testnet").attached_deposit(10u128.pow(25)).block_timestamp(u64::MAX / 2).build());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.subscribe("bob_near".parse().unwrap(), None, None);
+        }));
+        assert_contract_error(result, ContractError::InvalidInput(String::new()));
+    }
+
+    /// returns a contract where "creator" charges 100 of the "usdc.near" token
+    fn token_priced_contract() -> Contract {
+        testing_env!(
+            VMContextBuilder::new()
+                .current_account_id("contract.near".parse().unwrap())
+                .predecessor_account_id("owner.near".parse().unwrap())
+                .build()
+        );
+        let mut contract = registered_contract(&["creator", "consumer"]);
+        contract.add_accepted_token("usdc.near".parse().unwrap());
+
+        testing_env!(get_context(false, "creator".parse().unwrap(), 10u128.pow(25)));
+        contract.add_profile(
+            "creator".parse().unwrap(),
+            "creator".to_owned(),
+            "1".to_owned(),
+            "3".to_owned()
+        );
+        contract.add_content("date".to_owned(), text_content("content test"));
+        contract.set_token_cost("usdc.near".parse().unwrap(), U128::from(100));
+        contract
+    }
+
+    #[test]
+    fn test_ft_on_transfer() {
+        let mut contract = token_priced_contract();
+        assert_eq!(
+            Some(U128::from(100)),
+            contract.get_token_cost("creator".parse().unwrap(), "usdc.near".parse().unwrap())
+        );
+        testing_env!(get_call_context("consumer")
+            .current_account_id("contract.near".parse().unwrap())
+            .predecessor_account_id("usdc.near".parse().unwrap())
+            .build());
+        let unused = match contract.ft_on_transfer(
+            "consumer".parse().unwrap(),
+            U128::from(150),
+            "{\"creator_address\": \"creator\"}".to_owned()
+        ) {
+            PromiseOrValue::Value(unused) => unused,
+            PromiseOrValue::Promise(_) => panic!()
+        };
+        assert_eq!(U128::from(50), unused);
+        // the payment is held until the creator withdraws it
+        assert!(get_created_receipts().is_empty());
+        assert_eq!(
+            U128::from(100),
+            contract.withdrawable_token_balance("creator".parse().unwrap(), "usdc.near".parse().unwrap())
+        );
+
+        testing_env!(get_context(false, "consumer".parse().unwrap(), 10u128.pow(25)));
+        assert_eq!(
+            "content test".to_owned(),
+            contract.get_content("creator".parse().unwrap(), "date".to_owned()).body
+        );
+    }
+
+    #[test]
+    fn test_token_payments_are_final() {
+        let mut contract = token_priced_contract();
+        testing_env!(get_call_context("creator").block_timestamp(0).build());
+        contract.set_max_post_gap(Some("1000".to_owned()), None);
+        testing_env!(get_call_context("consumer")
+            .current_account_id("contract.near".parse().unwrap())
+            .predecessor_account_id("usdc.near".parse().unwrap())
+            .block_timestamp(100)
+            .build());
+        contract.ft_on_transfer(
+            "consumer".parse().unwrap(),
+            U128::from(100),
+            "{\"creator_address\": \"creator\"}".to_owned()
+        );
+
+        // the creator missed their commitment, but only NEAR payments are refunded
+        testing_env!(get_call_context("consumer").block_timestamp(u64::MAX / 2).build());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.claim_refund("creator".parse().unwrap());
+        }));
+        assert_contract_error(result, ContractError::NothingToRefund);
+        assert_eq!(
+            U128::from(100),
+            contract.withdrawable_token_balance("creator".parse().unwrap(), "usdc.near".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_ft_on_transfer_rejected() {
+        let mut contract = token_priced_contract();
+        for (token_id, amount, expected_error) in [
+            ("usdc.near", 99, ContractError::InsufficientDeposit { required: 0, attached: 0 }),
+            ("fake_usdc.near", 100, ContractError::TokenNotAccepted)
+        ] {
+            testing_env!(get_call_context("consumer")
+                .current_account_id("contract.near".parse().unwrap())
+                .predecessor_account_id(token_id.parse().unwrap())
+                .build());
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(||
+                {
+                    contract.ft_on_transfer(
+                        "consumer".parse().unwrap(),
+                        U128::from(amount),
+                        "{\"creator_address\": \"creator\"}".to_owned()
+                    )
+                }
+            ));
+            assert_contract_error(result, expected_error);
+        }
+    }
+
+    #[test]
+    fn test_content_item() {
+        let mut context = get_context(
+            false,
+            "creator".parse().unwrap(),
+            10u128.pow(25)
+        );
+        context.block_timestamp = 42;
+        testing_env!(context);
+        let mut contract = registered_contract(&["creator"]);
+        contract.add_profile(
+            "creator".parse().unwrap(),
+            "creator".to_owned(),
+            "1".to_owned(),
+            "3".to_owned()
+        );
+        contract.add_content(
+            "1".to_owned(),
+            NewContent {
+                title: "first video".to_owned(),
+                body: "https://www.youtube.com/watch?v=MddGbXgIt2E".to_owned(),
+                mime_type: "video/mp4".to_owned(),
+                content_hash: Some("hash".to_owned()),
+                visibility: None
+            }
+        );
+        assert_eq!(
+            ContentItem {
+                title: "first video".to_owned(),
+                body: "https://www.youtube.com/watch?v=MddGbXgIt2E".to_owned(),
+                mime_type: "video/mp4".to_owned(),
+                created_at: 42,
+                sequence: 1,
+                content_hash: Some("hash".to_owned())
+            },
+            contract.get_content("creator".parse().unwrap(), "1".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_legacy_content_readable() {
+        let context = get_context(
+            false,
+            "bob_near".parse().unwrap(),
+            10u128.pow(25)
+        );
+        testing_env!(context);
+        let mut contract = new_contract();
+        let profile = contract.internal_get_profile(&"bob_near".parse().unwrap()).unwrap();
+        let mut legacy_content = profile.content;
+        legacy_content.insert(&"old date".to_owned(), &"old content".to_owned());
+        assert_eq!(
+            ContentItem::from_legacy("old content".to_owned()),
+            contract.get_content("bob_near".parse().unwrap(), "old date".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_list_content() {
+        let mut context = get_context(
+            false,
+            "creator".parse().unwrap(),
+            10u128.pow(25)
+        );
+        testing_env!(context.clone());
+        let mut contract = registered_contract(&["creator"]);
+        contract.add_profile(
+            "creator".parse().unwrap(),
+            "creator".to_owned(),
+            "1".to_owned(),
+            "3".to_owned()
+        );
+        for post in 1..=5 {
+            context.block_timestamp = post;
+            testing_env!(context.clone());
+            contract.add_content(format!("post {}", post), text_content(&format!("content {}", post)));
+        }
+        // replacing content keeps its place in the listing
+        contract.add_content("post 2".to_owned(), text_content("content 2 edited"));
+
+        testing_env!(get_context(false, "consumer".parse().unwrap(), 10u128.pow(25)));
+        let listed = contract.list_content("creator".parse().unwrap(), Some(1), Some(2));
+        assert_eq!(
+            vec![
+                ContentMetadata {
+                    id: "post 2".to_owned(),
+                    title: "test".to_owned(),
+                    sequence: 2,
+                    created_at: 2,
+                    visibility: Visibility::SubscribersOnly
+                },
+                ContentMetadata {
+                    id: "post 3".to_owned(),
+                    title: "test".to_owned(),
+                    sequence: 3,
+                    created_at: 3,
+                    visibility: Visibility::SubscribersOnly
+                }
+            ],
+            listed
+        );
+        assert_eq!(5, contract.list_content("creator".parse().unwrap(), None, None).len());
+        assert!(contract.list_content("creator".parse().unwrap(), Some(5), Some(2)).is_empty());
+        // an edit isn't a new post, so it doesn't use up subscribers credit
+        assert_eq!(5, contract.get_profile("creator".parse().unwrap()).unwrap().content_count);
+
+        let latest: Vec<String> = contract.latest_content("creator".parse().unwrap(), 2)
+            .into_iter()
+            .map(|content| content.id)
+            .collect();
+        assert_eq!(vec!["post 5".to_owned(), "post 4".to_owned()], latest);
+        assert_eq!(5, contract.latest_content("creator".parse().unwrap(), 20).len());
+    }
+
+    #[test]
+    fn test_get_profile_view() {
+        let context = get_call_context("dan.testnet").attached_deposit(10u128.pow(25)).build();
+        testing_env!(context);
+        let mut contract = registered_contract(&["dan.testnet"]);
+        assert_eq!(None, contract.get_profile("dan.testnet".parse().unwrap()));
+        contract.subscribe("bob_near".parse().unwrap(), None, None);
+        assert_eq!(
+            Some(ProfileView {
+                profile_type: ProfileType::Creator,
+                cost: U128::from(10u128.pow(25)),
+                payment_interval: 4,
+                access_mode: AccessMode::ContentCount,
+                content_count: 1,
+                subscriber_count: 1
+            }),
+            contract.get_profile("bob_near".parse().unwrap())
+        );
+        assert_eq!(
+            "{\"profile_type\":\"creator\",\"cost\":\"10000000000000000000000000\",\"payment_interval\":4,\"access_mode\":\"content_count\",\"content_count\":1,\"subscriber_count\":1}",
+            near_sdk::serde_json::to_string(&contract.get_profile("bob_near".parse().unwrap())).unwrap()
+        );
+    }
+
+    /// Events logged under the contracts own standard, leaving out the NEP-171 pass events
+    fn event_logs() -> Vec<String> {
+        get_logs()
+            .into_iter()
+            .filter(|log| log.starts_with("EVENT_JSON:{\"standard\":\"content_subscription\""))
+            .collect()
+    }
+
+    #[test]
+    fn test_add_profile_event() {
+        testing_env!(get_context(false, "dan.testnet".parse().unwrap(), 10u128.pow(25)));
+        let mut contract = registered_contract(&["dan.testnet"]);
+        contract.add_profile(
+            "dan.testnet".parse().unwrap(),
+            "creator".to_owned(),
+            "1".to_owned(),
+            "4".to_owned()
+        );
+        assert_eq!(
+            vec!["EVENT_JSON:{\"standard\":\"content_subscription\",\"version\":\"1.0.0\",\"event\":\"profile_created\",\"data\":[{\"creator\":\"dan.testnet\",\"profile_type\":\"creator\",\"cost\":\"1000000000000000000000000\",\"payment_interval\":4}]}".to_owned()],
+            event_logs()
+        );
+    }
+
+    #[test]
+    fn test_subscribe_events() {
+        testing_env!(get_call_context("dan.testnet").attached_deposit(10u128.pow(25)).build());
+        let mut contract = registered_contract(&["dan.testnet"]);
+        contract.subscribe("bob_near".parse().unwrap(), None, None);
+        let logs = event_logs();
+        assert_eq!(1, logs.len());
+        assert!(logs[0].contains("\"event\":\"subscribed\""));
+        assert!(logs[0].contains("\"subscriber\":\"dan.testnet\""));
+        assert!(logs[0].contains("\"token_id\":null"));
+        assert!(logs[0].contains("\"amount\":\"10000000000000000000000000\""));
+    }
+
+    #[test]
+    fn test_ft_on_transfer_events() {
+        let mut contract = token_priced_contract();
+        testing_env!(get_call_context("consumer")
+            .current_account_id("contract.near".parse().unwrap())
+            .predecessor_account_id("usdc.near".parse().unwrap())
+            .build());
+        contract.ft_on_transfer(
+            "consumer".parse().unwrap(),
+            U128::from(100),
+            "{\"creator_address\": \"creator\"}".to_owned()
+        );
+        let logs = event_logs();
+        assert_eq!(1, logs.len());
+        assert!(logs[0].contains("\"event\":\"subscribed\""));
+        assert!(logs[0].contains("\"token_id\":\"usdc.near\""));
+        assert!(logs[0].contains("\"amount\":\"100\""));
+    }
+
+    #[test]
+    fn test_withdraw_token() {
+        let mut contract = token_priced_contract();
+        testing_env!(get_call_context("consumer")
+            .current_account_id("contract.near".parse().unwrap())
+            .predecessor_account_id("usdc.near".parse().unwrap())
+            .build());
+        contract.ft_on_transfer(
+            "consumer".parse().unwrap(),
+            U128::from(100),
+            "{\"creator_address\": \"creator\"}".to_owned()
+        );
+
+        testing_env!(get_call_context("creator").attached_deposit(1).build());
+        contract.withdraw_token("usdc.near".parse().unwrap(), U128::from(100));
+        let receipts = get_created_receipts();
+        assert_eq!("usdc.near", receipts[0].receiver_id.as_str());
+        assert_eq!(
+            U128::from(0),
+            contract.withdrawable_token_balance("creator".parse().unwrap(), "usdc.near".parse().unwrap())
+        );
+
+        // a failed ft_transfer, e.g. to an account not registered with the token, can be withdrawn again
+        testing_env!(
+            get_call_context(env::current_account_id().as_str()).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed]
+        );
+        assert!(!contract.on_withdraw_token(
+            "creator".parse().unwrap(),
+            "usdc.near".parse().unwrap(),
+            U128::from(100)
+        ));
+        assert_eq!(
+            U128::from(100),
+            contract.withdrawable_token_balance("creator".parse().unwrap(), "usdc.near".parse().unwrap())
+        );
+
+        testing_env!(get_call_context("creator").attached_deposit(1).build());
+        contract.withdraw_token("usdc.near".parse().unwrap(), U128::from(100));
+        testing_env!(
+            get_call_context(env::current_account_id().as_str()).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])]
+        );
+        assert!(contract.on_withdraw_token(
+            "creator".parse().unwrap(),
+            "usdc.near".parse().unwrap(),
+            U128::from(100)
+        ));
+        assert!(event_logs()[0].contains("\"token_id\":\"usdc.near\""));
+        testing_env!(get_call_context("creator").attached_deposit(1).build());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.withdraw_token("usdc.near".parse().unwrap(), U128::from(1));
+        }));
+        assert_contract_error(result, ContractError::InsufficientBalance { required: 0, available: 0 });
+    }
+
+    #[test]
+    fn test_migrate_from_first_deployment() {
+        testing_env!(get_context(false, "creator".parse().unwrap(), 10u128.pow(25)));
+        // write the state and profile layouts the contract was first deployed with
+        let mut content = LookupMap::new(StorageKeys::Content);
+        content.insert(&"date".to_owned(), &"legacy content".to_owned());
+        let mut costs = LookupMap::new(StorageKeys::Cost);
+        costs.insert(&"cost".to_owned(), &U128::from(10u128.pow(24)));
+        let mut content_count = LookupMap::new(StorageKeys::ContentCount);
+        content_count.insert(&"content_count".to_owned(), &1);
+        let profile = (
+            ProfileType::Creator,
+            3i32,
+            content_count,
+            content,
+            LookupMap::<AccountId, i32>::new(StorageKeys::Subscribers),
+            costs
+        );
+        let mut data = LookupMap::new(StorageKeys::Data);
+        data.insert(&"creator".parse::<AccountId>().unwrap(), &profile);
+        env::state_write(&data);
+
+        let mut contract = Contract::migrate();
+        // the migrated state is stored with its version from now on
+        assert_eq!(Some(vec![1]), env::storage_read(b"STATE_VERSION"));
+        assert_eq!(env::current_account_id(), contract.get_owner());
+        let view = contract.get_profile("creator".parse().unwrap()).unwrap();
+        assert_eq!(3, view.payment_interval);
+        assert_eq!(U128::from(10u128.pow(24)), view.cost);
+        assert_eq!(
+            "legacy content".to_owned(),
+            contract.get_content("creator".parse().unwrap(), "date".to_owned()).body
+        );
+    }
+
+    #[test]
+    fn test_migrate_current_state() {
+        testing_env!(get_call_context("owner.near").attached_deposit(10u128.pow(25)).build());
+        let mut contract = registered_contract(&["creator"]);
+        contract.add_accepted_token("usdc.near".parse().unwrap());
+        testing_env!(get_context(false, "creator".parse().unwrap(), 10u128.pow(25)));
+        contract.add_profile(
+            "creator".parse().unwrap(),
+            "creator".to_owned(),
+            "1".to_owned(),
+            "4".to_owned()
+        );
+        env::state_write(&contract);
+
+        let contract = Contract::migrate();
+        assert_eq!(vec!["usdc.near".parse::<AccountId>().unwrap()], contract.get_accepted_tokens());
+        assert_eq!(4, contract.get_profile("creator".parse().unwrap()).unwrap().payment_interval);
+        assert!(contract.storage_balance_of("creator".parse().unwrap()).is_some());
+    }
+
+    #[test]
+    fn test_migrate_reads_state_version() {
+        testing_env!(get_call_context("owner.near").attached_deposit(10u128.pow(25)).build());
+        let contract = new_contract();
+        env::state_write(&contract);
+        env::storage_write(b"STATE_VERSION", &[200]);
+        let result = std::panic::catch_unwind(|| {
+            Contract::migrate();
+        });
+        assert_contract_error(result, ContractError::InvalidState(String::new()));
+
+        // the stored version is trusted rather than matching the state against older layouts
+        env::state_write(&LookupMap::<AccountId, u8>::new(StorageKeys::Data));
+        env::storage_write(b"STATE_VERSION", &[1]);
+        let result = std::panic::catch_unwind(|| {
+            Contract::migrate();
+        });
+        assert_contract_error(result, ContractError::InvalidState(String::new()));
+    }
+
+    #[test]
+    fn test_upgrade() {
+        let context = VMContextBuilder::new()
+            .current_account_id("contract.near".parse().unwrap())
+            .predecessor_account_id("owner.near".parse().unwrap())
+            .build();
+        testing_env!(context);
+        let contract = new_contract();
+        contract.internal_upgrade(vec![0, 97, 115, 109]);
+        let receipts = get_created_receipts();
+        assert_eq!(1, receipts.len());
+        assert_eq!("contract.near", receipts[0].receiver_id.as_str());
+        assert_eq!(2, receipts[0].actions.len());
+
+        let context = VMContextBuilder::new()
+            .current_account_id("contract.near".parse().unwrap())
+            .predecessor_account_id("consumer".parse().unwrap())
+            .build();
+        testing_env!(context);
+        let result = std::panic::catch_unwind(|| {
+            new_contract().internal_upgrade(vec![0, 97, 115, 109]);
+        });
+        assert_contract_error(result, ContractError::NotOwner);
+    }
+
+    #[test]
+    fn test_admin_roles() {
+        testing_env!(get_call_context("owner.near").attached_deposit(10u128.pow(25)).build());
+        let mut contract = new_contract();
+        assert_eq!("owner.near", contract.get_owner().as_str());
+        contract.add_admin("admin.near".parse().unwrap());
+        assert_eq!(vec!["admin.near".parse::<AccountId>().unwrap()], contract.get_admins());
+
+        testing_env!(get_call_context("admin.near").attached_deposit(10u128.pow(25)).build());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.add_admin("consumer".parse().unwrap());
+        }));
+        assert_contract_error(result, ContractError::NotOwner);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.add_accepted_token("usdc.near".parse().unwrap());
+        }));
+        assert_contract_error(result, ContractError::NotOwner);
+        contract.pause();
+        assert!(contract.is_paused());
+        contract.unpause();
+
+        testing_env!(get_call_context("owner.near").attached_deposit(10u128.pow(25)).build());
+        contract.remove_admin("admin.near".parse().unwrap());
+        contract.set_owner("new_owner.near".parse().unwrap());
+        assert_eq!("new_owner.near", contract.get_owner().as_str());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.pause();
+        }));
+        assert_contract_error(result, ContractError::NotAdmin);
+
+        testing_env!(get_call_context("admin.near").attached_deposit(10u128.pow(25)).build());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.pause();
+        }));
+        assert_contract_error(result, ContractError::NotAdmin);
+        assert!(!contract.is_paused());
+    }
+
+    #[test]
+    fn test_pause() {
+        testing_env!(get_call_context("owner.near").attached_deposit(10u128.pow(25)).build());
+        let mut contract = registered_contract(&["consumer"]);
+        contract.pause();
+
+        testing_env!(get_call_context("consumer").attached_deposit(10u128.pow(25)).build());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.subscribe("bob_near".parse().unwrap(), None, None);
+        }));
+        assert_contract_error(result, ContractError::Paused);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.add_profile(
+                "consumer".parse().unwrap(),
+                "creator".to_owned(),
+                "1".to_owned(),
+                "4".to_owned()
+            );
+        }));
+        assert_contract_error(result, ContractError::Paused);
+
+        testing_env!(get_call_context("bob_near").attached_deposit(10u128.pow(25)).build());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.add_content("new date".to_owned(), text_content("paused"));
+        }));
+        assert_contract_error(result, ContractError::Paused);
+        assert_eq!(
+            "content test".to_owned(),
+            contract.get_content("bob_near".parse().unwrap(), "date".to_owned()).body
+        );
+        assert_eq!(1, contract.get_profile("bob_near".parse().unwrap()).unwrap().content_count);
+
+        testing_env!(get_call_context("owner.near").attached_deposit(10u128.pow(25)).build());
+        contract.unpause();
+        testing_env!(get_call_context("consumer").attached_deposit(10u128.pow(25)).build());
+        contract.subscribe("bob_near".parse().unwrap(), None, None);
+        assert_eq!(1, contract.get_profile("bob_near".parse().unwrap()).unwrap().subscriber_count);
+    }
+
+    #[test]
+    fn test_creator_contract() {
+        testing_env!(get_call_context("creator.factory.near").attached_deposit(10u128.pow(25)).build());
+        let mut contract = Contract::new_creator_contract(
+            "factory.near".parse().unwrap(),
+            "creator.near".parse().unwrap(),
+            "1".to_owned(),
+            "3".to_owned()
+        );
+        assert_eq!(Some("creator.near".parse().unwrap()), contract.get_creator_id());
+        assert_eq!("factory.near", contract.get_owner().as_str());
+        assert_eq!(3, contract.get_profile("creator.near".parse().unwrap()).unwrap().payment_interval);
+
+        // the creator posts without registering for storage
+        testing_env!(get_call_context("creator.near").attached_deposit(10u128.pow(25)).build());
+        contract.add_content("date".to_owned(), text_content("creator content"));
+        assert_eq!(1, contract.get_profile("creator.near".parse().unwrap()).unwrap().content_count);
+        assert!(contract.storage_balance_of("creator.near".parse().unwrap()).is_none());
+
+        testing_env!(get_call_context("consumer").attached_deposit(10u128.pow(25)).build());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.add_profile(
+                "consumer".parse().unwrap(),
+                "creator".to_owned(),
+                "1".to_owned(),
+                "4".to_owned()
+            );
+        }));
+        assert_contract_error(result, ContractError::InvalidState(String::new()));
+    }
+
+    #[test]
+    fn test_withdraw() {
+        testing_env!(get_call_context("dan.testnet").attached_deposit(10u128.pow(25)).build());
+        let mut contract = registered_contract(&["dan.testnet"]);
+        contract.subscribe("bob_near".parse().unwrap(), None, None);
+
+        testing_env!(get_call_context("bob_near").attached_deposit(1).build());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.withdraw(U128::from(10u128.pow(25) + 1));
+        }));
+        assert_contract_error(result, ContractError::InsufficientBalance { required: 0, available: 0 });
+        contract.withdraw(U128::from(4 * 10u128.pow(24)));
+        let receipts = get_created_receipts();
+        assert_eq!(2, receipts.len());
+        assert_eq!("bob_near", receipts[0].receiver_id.as_str());
+        // the pending withdrawal can't be withdrawn again before it resolves
+        assert_eq!(
+            U128::from(6 * 10u128.pow(24)),
+            contract.withdrawable_balance("bob_near".parse().unwrap())
+        );
+
+        // a failed transfer makes the amount withdrawable again
+        testing_env!(
+            get_call_context(env::current_account_id().as_str()).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed]
+        );
+        assert!(!contract.on_withdraw("bob_near".parse().unwrap(), U128::from(4 * 10u128.pow(24))));
+        assert_eq!(
+            U128::from(10u128.pow(25)),
+            contract.withdrawable_balance("bob_near".parse().unwrap())
+        );
+
+        testing_env!(get_call_context("bob_near").attached_deposit(1).build());
+        contract.withdraw(U128::from(4 * 10u128.pow(24)));
+        testing_env!(
+            get_call_context(env::current_account_id().as_str()).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])]
+        );
+        assert!(contract.on_withdraw("bob_near".parse().unwrap(), U128::from(4 * 10u128.pow(24))));
+        assert_eq!(
+            U128::from(6 * 10u128.pow(24)),
+            contract.withdrawable_balance("bob_near".parse().unwrap())
+        );
+        assert!(event_logs()[0].contains("\"event\":\"withdrawn\""));
+    }
+
+    #[test]
+    fn test_claim_refund() {
+        testing_env!(get_call_context("bob_near").block_timestamp(0).build());
+        let mut contract = registered_contract(&["bob_near", "dan.testnet"]);
+        contract.set_max_post_gap(Some("1000".to_owned()), None);
+        assert_eq!(Some(PostGap::Nanoseconds(1000)), contract.get_max_post_gap("bob_near".parse().unwrap()));
+
+        testing_env!(get_call_context("dan.testnet").attached_deposit(10u128.pow(25)).block_timestamp(100).build());
+        contract.subscribe("bob_near".parse().unwrap(), None, None);
+        testing_env!(get_call_context("bob_near").block_timestamp(500).build());
+        contract.add_content("second date".to_owned(), text_content("second post"));
+
+        // the creator posted within the last 1000 nanoseconds
+        testing_env!(get_call_context("dan.testnet").block_timestamp(1200).build());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.claim_refund("bob_near".parse().unwrap());
+        }));
+        assert_contract_error(result, ContractError::RefundNotDue);
+
+        // 1 of the 4 posts paid for was published
+        testing_env!(get_call_context("dan.testnet").block_timestamp(1600).build());
+        assert_eq!(
+            U128::from(75 * 10u128.pow(23)),
+            contract.claim_refund("bob_near".parse().unwrap())
+        );
+        let receipts = get_created_receipts();
+        assert_eq!(1, receipts.len());
+        assert_eq!("dan.testnet", receipts[0].receiver_id.as_str());
+        assert_eq!(
+            U128::from(25 * 10u128.pow(23)),
+            contract.withdrawable_balance("bob_near".parse().unwrap())
+        );
+        assert!(event_logs()[0].contains("\"event\":\"refunded\""));
+        assert_eq!(None, contract.get_pass("1".to_owned()));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.get_content("bob_near".parse().unwrap(), "date".to_owned());
+        }));
+        assert_contract_error(result, ContractError::SubscriptionExhausted);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.claim_refund("bob_near".parse().unwrap());
+        }));
+        assert_contract_error(result, ContractError::NothingToRefund);
+    }
+
+    #[test]
+    fn test_claim_refund_uses_payment_split() {
+        testing_env!(get_call_context("bob_near").block_timestamp(0).build());
+        let mut contract = registered_contract(&["bob_near", "dan.testnet"]);
+        contract.set_max_post_gap(Some("1000".to_owned()), None);
+        contract.set_revenue_split(vec![payee("bob_near", 7000), payee("alice.near", 3000)]);
+        testing_env!(get_call_context("dan.testnet").attached_deposit(10u128.pow(25)).block_timestamp(100).build());
+        contract.subscribe("bob_near".parse().unwrap(), None, None);
+
+        // changing the split afterwards doesn't move the refund onto other accounts
+        testing_env!(get_call_context("bob_near").block_timestamp(200).build());
+        contract.set_revenue_split(vec![]);
+        testing_env!(get_call_context("dan.testnet").block_timestamp(1600).build());
+        assert_eq!(U128::from(10u128.pow(25)), contract.claim_refund("bob_near".parse().unwrap()));
+        assert_eq!(U128::from(0), contract.withdrawable_balance("bob_near".parse().unwrap()));
+        assert_eq!(U128::from(0), contract.withdrawable_balance("alice.near".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_claim_refund_in_blocks() {
+        testing_env!(get_call_context("bob_near").block_index(10).build());
+        let mut contract = registered_contract(&["bob_near", "dan.testnet"]);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.set_max_post_gap(Some("1000".to_owned()), Some("100".to_owned()));
+        }));
+        assert_contract_error(result, ContractError::InvalidInput(String::new()));
+        contract.set_max_post_gap(None, Some("100".to_owned()));
+        assert_eq!(Some(PostGap::Blocks(100)), contract.get_max_post_gap("bob_near".parse().unwrap()));
+        testing_env!(get_call_context("dan.testnet").attached_deposit(10u128.pow(25)).block_index(20).build());
+        contract.subscribe("bob_near".parse().unwrap(), None, None);
+        testing_env!(get_call_context("bob_near").block_index(50).build());
+        contract.add_content("second date".to_owned(), text_content("second post"));
+
+        // however much block time passes, the gap is counted from the last post in blocks
+        testing_env!(get_call_context("dan.testnet").block_index(150).block_timestamp(u64::MAX / 2).build());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.claim_refund("bob_near".parse().unwrap());
+        }));
+        assert_contract_error(result, ContractError::RefundNotDue);
+        testing_env!(get_call_context("dan.testnet").block_index(151).build());
+        assert_eq!(
+            U128::from(75 * 10u128.pow(23)),
+            contract.claim_refund("bob_near".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_claim_refund_after_withdrawal() {
+        testing_env!(get_call_context("bob_near").block_timestamp(0).build());
+        let mut contract = registered_contract(&["bob_near", "dan.testnet"]);
+        contract.set_max_post_gap(Some("1000".to_owned()), None);
+        testing_env!(get_call_context("dan.testnet").attached_deposit(10u128.pow(25)).block_timestamp(100).build());
+        contract.subscribe("bob_near".parse().unwrap(), None, None);
+        testing_env!(get_call_context("bob_near").attached_deposit(1).build());
+        contract.withdraw(U128::from(5 * 10u128.pow(24)));
+
+        // only part of the refund is left, so the call fails rather than paying part of it
+        testing_env!(get_call_context("dan.testnet").block_timestamp(1600).build());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.claim_refund("bob_near".parse().unwrap());
+        }));
+        assert_contract_error(result, ContractError::RefundUnavailable { owed: 0, available: 0 });
+    }
+
+    #[test]
+    fn test_claim_refund_without_commitment() {
+        testing_env!(get_call_context("dan.testnet").attached_deposit(10u128.pow(25)).block_timestamp(0).build());
+        let mut contract = registered_contract(&["dan.testnet"]);
+        contract.subscribe("bob_near".parse().unwrap(), None, None);
+        testing_env!(get_call_context("dan.testnet").block_timestamp(u64::MAX / 2).build());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.claim_refund("bob_near".parse().unwrap());
+        }));
+        assert_contract_error(result, ContractError::NothingToRefund);
+    }
+
+    #[test]
+    fn test_subscribe_for() {
+        testing_env!(get_call_context("dan.testnet").attached_deposit(2 * 10u128.pow(25)).build());
+        let mut contract = registered_contract(&["dan.testnet"]);
+        contract.subscribe_for("bob_near".parse().unwrap(), "eve.testnet".parse().unwrap(), None);
+        let profile = contract.internal_get_profile(&"bob_near".parse().unwrap()).unwrap();
+        assert_eq!(Some(1), profile.subscribers.get(&"eve.testnet".parse().unwrap()));
+        assert_eq!(None, profile.subscribers.get(&"dan.testnet".parse().unwrap()));
+        // the payer gets the overpayment back
+        let receipts = get_created_receipts();
+        assert_eq!(1, receipts.len());
+        assert_eq!("dan.testnet", receipts[0].receiver_id.as_str());
+        assert_eq!(
+            U128::from(10u128.pow(25)),
+            contract.withdrawable_balance("bob_near".parse().unwrap())
+        );
+        let logs = event_logs();
+        assert_eq!(2, logs.len());
+        assert!(logs[0].contains("\"subscriber\":\"eve.testnet\""));
+        assert!(logs[1].contains("\"event\":\"subscription_gifted\""));
+        assert!(logs[1].contains("\"payer\":\"dan.testnet\""));
+        assert!(logs[1].contains("\"beneficiary\":\"eve.testnet\""));
+
+        testing_env!(get_context(false, "eve.testnet".parse().unwrap(), 10u128.pow(25)));
+        assert_eq!(
+            "content test".to_owned(),
+            contract.get_content("bob_near".parse().unwrap(), "date".to_owned()).body
+        );
+    }
+
+    #[test]
+    fn test_gift_subscription() {
+        testing_env!(get_call_context("dan.testnet").attached_deposit(10u128.pow(25)).build());
+        let mut contract = registered_contract(&["dan.testnet"]);
+        contract.gift_subscription(
+            "bob_near".parse().unwrap(),
+            "eve.testnet".parse().unwrap(),
+            None,
+            Some("happy birthday".to_owned())
+        );
+        let profile = contract.internal_get_profile(&"bob_near".parse().unwrap()).unwrap();
+        assert_eq!(Some(1), profile.subscribers.get(&"eve.testnet".parse().unwrap()));
+        let logs = event_logs();
+        assert_eq!(2, logs.len());
+        assert!(logs[1].contains("\"message\":\"happy birthday\""));
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.gift_subscription(
+                "bob_near".parse().unwrap(),
+                "dan.testnet".parse().unwrap(),
+                None,
+                None
+            );
+        }));
+        assert_contract_error(result, ContractError::InvalidInput(String::new()));
+    }
+
+    #[test]
+    fn test_ft_on_transfer_gift() {
+        let mut contract = token_priced_contract();
+        testing_env!(get_call_context("consumer")
+            .current_account_id("contract.near".parse().unwrap())
+            .predecessor_account_id("usdc.near".parse().unwrap())
+            .build());
+        contract.ft_on_transfer(
+            "consumer".parse().unwrap(),
+            U128::from(100),
+            "{\"creator_address\": \"creator\", \"beneficiary\": \"friend\"}".to_owned()
+        );
+        let profile = contract.internal_get_profile(&"creator".parse().unwrap()).unwrap();
+        assert_eq!(Some(1), profile.subscribers.get(&"friend".parse().unwrap()));
+        assert_eq!(None, profile.subscribers.get(&"consumer".parse().unwrap()));
+        let logs = event_logs();
+        assert_eq!(2, logs.len());
+        assert!(logs[1].contains("\"event\":\"subscription_gifted\""));
+        assert!(logs[1].contains("\"payer\":\"consumer\""));
+        assert!(logs[1].contains("\"token_id\":\"usdc.near\""));
+    }
+
+    #[test]
+    fn test_subscription_pass() {
+        testing_env!(get_call_context("dan.testnet").attached_deposit(10u128.pow(25)).build());
+        let mut contract = registered_contract(&["dan.testnet"]);
+        contract.subscribe("bob_near".parse().unwrap(), None, None);
+        assert!(get_logs()[0].contains("\"event\":\"nft_mint\""));
+        assert_eq!(U128::from(1), contract.nft_total_supply());
+        let tokens = contract.nft_tokens_for_owner("dan.testnet".parse().unwrap(), None, None);
+        assert_eq!(1, tokens.len());
+        assert_eq!("1", tokens[0].token_id);
+        let pass = Pass {
+            creator: "bob_near".parse().unwrap(),
+            profile_nonce: 1,
+            content_count: 1,
+            payment_interval: 4,
+            expires_at: None
+        };
+        assert_eq!(
+            Some(near_sdk::serde_json::to_string(&pass).unwrap()),
+            tokens[0].metadata.as_ref().unwrap().extra
+        );
+        assert_eq!(Some(pass), contract.get_pass("1".to_owned()));
+        assert_eq!(NFT_METADATA_SPEC, contract.nft_metadata().spec);
+    }
+
+    #[test]
+    fn test_pass_transfer() {
+        testing_env!(get_call_context("dan.testnet").attached_deposit(10u128.pow(25)).build());
+        let mut contract = registered_contract(&["dan.testnet"]);
+        contract.subscribe("bob_near".parse().unwrap(), None, None);
+        testing_env!(get_call_context("dan.testnet").attached_deposit(1).build());
+        let available = contract.storage_balance_of("dan.testnet".parse().unwrap()).unwrap().available.0;
+        contract.nft_transfer("carol.testnet".parse().unwrap(), "1".to_owned(), None, None);
+
+        // the contract pays for the storage the receivers token entry takes up
+        assert_eq!(
+            available,
+            contract.storage_balance_of("dan.testnet".parse().unwrap()).unwrap().available.0
+        );
+
+        // access moves with the pass
+        testing_env!(get_context(false, "carol.testnet".parse().unwrap(), 10u128.pow(25)));
+        assert_eq!(
+            "content test".to_owned(),
+            contract.get_content("bob_near".parse().unwrap(), "date".to_owned()).body
+        );
+        testing_env!(get_context(false, "dan.testnet".parse().unwrap(), 10u128.pow(25)));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.get_content("bob_near".parse().unwrap(), "date".to_owned());
+        }));
+        assert_contract_error(result, ContractError::SubscriptionExhausted);
+
+        // the sender can subscribe again straight away
+        testing_env!(get_call_context("dan.testnet").attached_deposit(10u128.pow(25)).build());
+        contract.subscribe("bob_near".parse().unwrap(), None, None);
+        assert_eq!(
+            "content test".to_owned(),
+            contract.get_content("bob_near".parse().unwrap(), "date".to_owned()).body
+        );
+        assert_eq!(U128::from(2), contract.nft_total_supply());
+
+        // a holder that never registered can pass it on
+        testing_env!(get_call_context("carol.testnet").attached_deposit(1).build());
+        contract.nft_transfer("eve.testnet".parse().unwrap(), "1".to_owned(), None, None);
+        assert_eq!("eve.testnet", contract.nft_token("1".to_owned()).unwrap().owner_id.as_str());
+    }
+
+    #[test]
+    fn test_pass_transfer_call() {
+        testing_env!(get_call_context("bob_near").build());
+        let mut contract = registered_contract(&["bob_near", "dan.testnet"]);
+        contract.set_max_post_gap(Some("1000".to_owned()), None);
+        testing_env!(get_call_context("dan.testnet").attached_deposit(10u128.pow(25)).build());
+        contract.subscribe("bob_near".parse().unwrap(), None, None);
+
+        // the refund record is held while the receiver decides
+        testing_env!(get_call_context("dan.testnet").attached_deposit(1).build());
+        contract.nft_transfer_call("carol.testnet".parse().unwrap(), "1".to_owned(), None, None, String::new());
+        let profile = contract.internal_get_profile(&"bob_near".parse().unwrap()).unwrap();
+        assert!(profile.payments.get(&"dan.testnet".parse().unwrap()).is_none());
+        assert!(profile.is_subscription_active(&"dan.testnet".parse().unwrap()) == Some(true));
+
+        // a returned pass gives the sender their refund record back
+        testing_env!(
+            get_call_context(env::current_account_id().as_str()).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(b"true".to_vec())]
+        );
+        assert!(!contract.nft_resolve_transfer(
+            "dan.testnet".parse().unwrap(),
+            "carol.testnet".parse().unwrap(),
+            "1".to_owned(),
+            None
+        ));
+        assert_eq!("dan.testnet", contract.nft_token("1".to_owned()).unwrap().owner_id.as_str());
+        let profile = contract.internal_get_profile(&"bob_near".parse().unwrap()).unwrap();
+        assert!(profile.payments.get(&"dan.testnet".parse().unwrap()).is_some());
+        assert!(contract.get_subscriptions("carol.testnet".parse().unwrap(), None, None).is_empty());
+
+        // a kept pass takes the subscription with it
+        testing_env!(get_call_context("dan.testnet").attached_deposit(1).build());
+        contract.nft_transfer_call("carol.testnet".parse().unwrap(), "1".to_owned(), None, None, String::new());
+        testing_env!(
+            get_call_context(env::current_account_id().as_str()).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(b"false".to_vec())]
+        );
+        assert!(contract.nft_resolve_transfer(
+            "dan.testnet".parse().unwrap(),
+            "carol.testnet".parse().unwrap(),
+            "1".to_owned(),
+            None
+        ));
+        assert_eq!("carol.testnet", contract.nft_token("1".to_owned()).unwrap().owner_id.as_str());
+        let profile = contract.internal_get_profile(&"bob_near".parse().unwrap()).unwrap();
+        assert!(profile.payments.get(&"dan.testnet".parse().unwrap()).is_none());
+        assert!(profile.is_subscription_active(&"dan.testnet".parse().unwrap()) != Some(true));
+        assert!(contract.get_subscriptions("dan.testnet".parse().unwrap(), None, None).is_empty());
+        assert_eq!(1, contract.get_subscriptions("carol.testnet".parse().unwrap(), None, None).len());
+    }
+
+    #[test]
+    fn test_expired_pass() {
+        testing_env!(get_context(false, "bob_near".parse().unwrap(), 10u128.pow(25)));
+        let mut contract = registered_contract(&["bob_near"]);
+        for date in ["2", "3", "4", "5"] {
+            contract.add_content(date.to_owned(), text_content("content test"));
+        }
+        testing_env!(get_call_context("dan.testnet").attached_deposit(1).build());
+        let pass = Pass {
+            creator: "bob_near".parse().unwrap(),
+            profile_nonce: 1,
+            content_count: 1,
+            payment_interval: 4,
+            expires_at: None
+        };
+        contract.internal_mint_pass(&"dan.testnet".parse().unwrap(), pass);
+        assert_eq!(
+            "content test".to_owned(),
+            contract.get_content("bob_near".parse().unwrap(), "5".to_owned()).body
+        );
+        testing_env!(get_context(false, "bob_near".parse().unwrap(), 10u128.pow(25)));
+        contract.add_content("6".to_owned(), text_content("content test"));
+        testing_env!(get_call_context("dan.testnet").attached_deposit(1).build());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.get_content("bob_near".parse().unwrap(), "6".to_owned());
+        }));
+        assert_contract_error(result, ContractError::SubscriptionExhausted);
+    }
+
+    #[test]
+    fn test_pass_expiry_follows_access_mode() {
+        testing_env!(get_context(false, "bob_near".parse().unwrap(), 10u128.pow(25)));
+        let contract = registered_contract(&["bob_near"]);
+        let creator: AccountId = "bob_near".parse().unwrap();
+        let subscriber: AccountId = "dan.testnet".parse().unwrap();
+        let mut profile = contract.internal_get_profile(&creator).unwrap();
+
+        // an expiry left over from an earlier period subscription doesn't apply to content count access
+        profile.expiries.insert(&subscriber, &100);
+        assert_eq!(None, profile.new_pass(&creator, &subscriber, 4).expires_at);
+
+        profile.set_access_mode(AccessMode::new("period", Some(30)));
+        assert_eq!(Some(100), profile.new_pass(&creator, &subscriber, 4).expires_at);
+    }
+
+    fn payee(account_id: &str, share_bps: u16) -> Payee {
+        Payee {
+            account_id: account_id.parse().unwrap(),
+            share_bps
+        }
+    }
+
+    #[test]
+    fn test_split_payment() {
+        testing_env!(get_context(false, "bob_near".parse().unwrap(), 10u128.pow(25)));
+        let contract = registered_contract(&["bob_near"]);
+        let mut profile = contract.internal_get_profile(&"bob_near".parse().unwrap()).unwrap();
+        let creator: AccountId = "bob_near".parse().unwrap();
+        assert_eq!(vec![(creator.clone(), 10)], profile.split_payment(&creator, 10));
+
+        // 3333 basis points of 10 is 3.333, the remainder of 1 goes to the creator
+        profile.set_payees(vec![payee("alice.near", 3333), payee("carol.near", 3333), payee("bob_near", 3334)]);
+        assert_eq!(
+            vec![
+                ("alice.near".parse().unwrap(), 3),
+                ("carol.near".parse().unwrap(), 3),
+                (creator.clone(), 4)
+            ],
+            profile.split_payment(&creator, 10)
+        );
+        profile.set_payees(vec![payee("alice.near", 5000), payee("carol.near", 5000)]);
+        assert_eq!(
+            vec![
+                ("alice.near".parse().unwrap(), 2),
+                ("carol.near".parse().unwrap(), 2),
+                (creator.clone(), 1)
+            ],
+            profile.split_payment(&creator, 5)
+        );
+    }
+
+    #[test]
+    fn test_revenue_split() {
+        testing_env!(get_context(false, "bob_near".parse().unwrap(), 10u128.pow(25)));
+        let mut contract = registered_contract(&["bob_near", "dan.testnet"]);
+        contract.set_revenue_split(vec![payee("bob_near", 7000), payee("alice.near", 3000)]);
+        assert_eq!(
+            vec![payee("bob_near", 7000), payee("alice.near", 3000)],
+            contract.get_revenue_split("bob_near".parse().unwrap())
+        );
+
+        testing_env!(get_call_context("dan.testnet").attached_deposit(10u128.pow(25)).build());
+        contract.subscribe("bob_near".parse().unwrap(), None, None);
+        assert_eq!(
+            U128::from(7 * 10u128.pow(24)),
+            contract.withdrawable_balance("bob_near".parse().unwrap())
+        );
+        assert_eq!(
+            U128::from(3 * 10u128.pow(24)),
+            contract.withdrawable_balance("alice.near".parse().unwrap())
+        );
+
+        testing_env!(get_context(false, "bob_near".parse().unwrap(), 10u128.pow(25)));
+        for payees in [
+            vec![payee("bob_near", 7000), payee("alice.near", 2000)],
+            vec![payee("bob_near", 10000), payee("alice.near", 0)],
+            vec![payee("alice.near", 5000), payee("alice.near", 5000)]
+        ] {
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                contract.set_revenue_split(payees.clone());
+            }));
+            assert_contract_error(result, ContractError::InvalidInput(String::new()));
+        }
+    }
+
+    #[test]
+    fn test_owner_calls_use_predecessor() {
+        testing_env!(get_call_context("bob_near").build());
+        let mut contract = registered_contract(&["bob_near"]);
+        // a contract bob_near calls can't change bob_nears profile on their behalf
+        testing_env!(get_call_context("bob_near")
+            .predecessor_account_id("evil.near".parse().unwrap())
+            .build());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.set_revenue_split(vec![payee("evil.near", 10000)]);
+        }));
+        assert_contract_error(result, ContractError::ProfileNotFound);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.update_cost("0".to_owned());
+        }));
+        assert_contract_error(result, ContractError::ProfileNotFound);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.add_profile(
+                "bob_near".parse().unwrap(),
+                "creator".to_owned(),
+                "1".to_owned(),
+                "4".to_owned()
+            );
+        }));
+        assert_contract_error(result, ContractError::InvalidInput(String::new()));
+        assert!(contract.get_revenue_split("bob_near".parse().unwrap()).is_empty());
+        assert_eq!(
+            U128::from(10u128.pow(25)),
+            contract.get_profile("bob_near".parse().unwrap()).unwrap().cost
+        );
+    }
+
+    #[test]
+    fn test_ft_on_transfer_split() {
+        let mut contract = token_priced_contract();
+        contract.set_revenue_split(vec![payee("creator", 5000), payee("editor", 5000)]);
+        testing_env!(get_call_context("consumer")
+            .current_account_id("contract.near".parse().unwrap())
+            .predecessor_account_id("usdc.near".parse().unwrap())
+            .build());
+        contract.ft_on_transfer(
+            "consumer".parse().unwrap(),
+            U128::from(101),
+            "{\"creator_address\": \"creator\"}".to_owned()
+        );
+        assert_eq!(
+            U128::from(50),
+            contract.withdrawable_token_balance("creator".parse().unwrap(), "usdc.near".parse().unwrap())
+        );
+        assert_eq!(
+            U128::from(50),
+            contract.withdrawable_token_balance("editor".parse().unwrap(), "usdc.near".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_platform_fee() {
+        testing_env!(get_call_context("bob_near").attached_deposit(10u128.pow(25)).build());
+        let mut contract = registered_contract(&["dan.testnet"]);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.set_platform_fee(500);
+        }));
+        assert_contract_error(result, ContractError::NotOwner);
+
+        testing_env!(get_call_context("owner.near").attached_deposit(10u128.pow(25)).build());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.set_platform_fee(10001);
+        }));
+        assert_contract_error(result, ContractError::InvalidInput(String::new()));
+        contract.set_platform_fee(500);
+
+        testing_env!(get_call_context("dan.testnet").attached_deposit(10u128.pow(25)).build());
+        contract.subscribe("bob_near".parse().unwrap(), None, None);
+        assert_eq!(
+            U128::from(95 * 10u128.pow(23)),
+            contract.withdrawable_balance("bob_near".parse().unwrap())
+        );
+        assert_eq!(
+            PlatformFeeView {
+                fee_bps: 500,
+                treasury_balance: U128::from(5 * 10u128.pow(23)),
+                fees_collected: U128::from(5 * 10u128.pow(23))
+            },
+            contract.get_platform_fee()
+        );
+
+        testing_env!(get_call_context("owner.near").attached_deposit(1).build());
+        contract.withdraw_fees(U128::from(2 * 10u128.pow(23)), None);
+        let receipts = get_created_receipts();
+        assert_eq!("owner.near", receipts[0].receiver_id.as_str());
+        testing_env!(
+            get_call_context(env::current_account_id().as_str()).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])]
+        );
+        assert!(contract.on_withdraw_fees("owner.near".parse().unwrap(), U128::from(2 * 10u128.pow(23))));
+
+        // a factory owned contract sends its fees on to another account
+        testing_env!(get_call_context("owner.near").attached_deposit(1).build());
+        contract.withdraw_fees(U128::from(3 * 10u128.pow(23)), Some("treasury.near".parse().unwrap()));
+        let receipts = get_created_receipts();
+        assert_eq!("treasury.near", receipts[0].receiver_id.as_str());
+        assert_eq!(U128::from(0), contract.get_platform_fee().treasury_balance);
+        testing_env!(
+            get_call_context(env::current_account_id().as_str()).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])]
+        );
+        assert!(contract.on_withdraw_fees("treasury.near".parse().unwrap(), U128::from(3 * 10u128.pow(23))));
+        assert_eq!(
+            PlatformFeeView {
+                fee_bps: 500,
+                treasury_balance: U128::from(0),
+                fees_collected: U128::from(5 * 10u128.pow(23))
+            },
+            contract.get_platform_fee()
+        );
+        assert!(event_logs()[0].contains("\"event\":\"fees_withdrawn\""));
+    }
+
+    #[test]
+    fn test_token_platform_fee() {
+        let mut contract = token_priced_contract();
+        testing_env!(get_call_context("owner.near").build());
+        contract.set_platform_fee(500);
+        testing_env!(get_call_context("consumer")
+            .current_account_id("contract.near".parse().unwrap())
+            .predecessor_account_id("usdc.near".parse().unwrap())
+            .build());
+        contract.ft_on_transfer(
+            "consumer".parse().unwrap(),
+            U128::from(100),
+            "{\"creator_address\": \"creator\"}".to_owned()
+        );
+        assert_eq!(
+            U128::from(95),
+            contract.withdrawable_token_balance("creator".parse().unwrap(), "usdc.near".parse().unwrap())
+        );
+        assert_eq!(U128::from(5), contract.get_token_fees("usdc.near".parse().unwrap()));
+
+        testing_env!(get_call_context("creator").attached_deposit(1).build());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.withdraw_token_fees("usdc.near".parse().unwrap(), U128::from(5), None);
+        }));
+        assert_contract_error(result, ContractError::NotOwner);
+
+        testing_env!(get_call_context("owner.near")
+            .current_account_id("contract.near".parse().unwrap())
+            .attached_deposit(1)
+            .build());
+        contract.withdraw_token_fees("usdc.near".parse().unwrap(), U128::from(5), Some("treasury.near".parse().unwrap()));
+        let receipts = get_created_receipts();
+        assert_eq!("usdc.near", receipts[0].receiver_id.as_str());
+        assert_eq!(U128::from(0), contract.get_token_fees("usdc.near".parse().unwrap()));
+        testing_env!(
+            get_call_context("contract.near").current_account_id("contract.near".parse().unwrap()).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])]
+        );
+        assert!(contract.on_withdraw_token_fees(
+            "treasury.near".parse().unwrap(),
+            "usdc.near".parse().unwrap(),
+            U128::from(5)
+        ));
+        assert_eq!(U128::from(0), contract.get_token_fees("usdc.near".parse().unwrap()));
+        assert!(event_logs()[0].contains("\"token_id\":\"usdc.near\""));
+    }
+
+    #[test]
+    fn test_content_visibility() {
+        testing_env!(get_context(false, "bob_near".parse().unwrap(), 10u128.pow(25)));
+        let mut contract = registered_contract(&["bob_near"]);
+        contract.add_content("public".to_owned(), NewContent {
+            visibility: Some(Visibility::Public),
+            ..text_content("public post")
+        });
+        contract.add_content("preview".to_owned(), NewContent {
+            visibility: Some(Visibility::Preview { excerpt: "the start".to_owned() }),
+            ..text_content("the start and the rest")
+        });
+        assert_eq!(
+            Visibility::Public,
+            contract.list_content("bob_near".parse().unwrap(), Some(1), Some(1))[0].visibility
+        );
+
+        testing_env!(get_context(false, "dan.testnet".parse().unwrap(), 10u128.pow(25)));
+        assert_eq!(
+            "public post".to_owned(),
+            contract.get_content("bob_near".parse().unwrap(), "public".to_owned()).body
+        );
+        assert_eq!(
+            "the start".to_owned(),
+            contract.get_content("bob_near".parse().unwrap(), "preview".to_owned()).body
+        );
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.get_content("bob_near".parse().unwrap(), "date".to_owned());
+        }));
+        assert_contract_error(result, ContractError::NotSubscriber);
+
+        // subscribers get the full body of previews
+        testing_env!(get_call_context("dan.testnet").attached_deposit(10u128.pow(25)).build());
+        contract.storage_deposit(None, None);
+        contract.subscribe("bob_near".parse().unwrap(), None, None);
+        assert_eq!(
+            "the start and the rest".to_owned(),
+            contract.get_content("bob_near".parse().unwrap(), "preview".to_owned()).body
+        );
+
+        testing_env!(get_context(false, "bob_near".parse().unwrap(), 10u128.pow(25)));
+        contract.set_content_visibility("public".to_owned(), Visibility::SubscribersOnly);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.set_content_visibility("missing".to_owned(), Visibility::Public);
+        }));
+        assert_contract_error(result, ContractError::ContentNotFound);
+        testing_env!(get_context(false, "eve.testnet".parse().unwrap(), 10u128.pow(25)));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.get_content("bob_near".parse().unwrap(), "public".to_owned());
+        }));
+        assert_contract_error(result, ContractError::NotSubscriber);
+    }
+
+    #[test]
+    fn test_purchase_content() {
+        testing_env!(get_context(false, "bob_near".parse().unwrap(), 10u128.pow(25)));
+        let mut contract = registered_contract(&["bob_near", "dan.testnet"]);
+        contract.add_content("second date".to_owned(), text_content("second post"));
+        contract.set_content_price("date".to_owned(), Some(U128::from(2 * 10u128.pow(24))));
+        assert_eq!(
+            Some(U128::from(2 * 10u128.pow(24))),
+            contract.get_content_price("bob_near".parse().unwrap(), "date".to_owned())
+        );
+
+        testing_env!(get_call_context("dan.testnet").attached_deposit(10u128.pow(24)).build());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.purchase_content("bob_near".parse().unwrap(), "date".to_owned());
+        }));
+        assert_contract_error(result, ContractError::InsufficientDeposit { required: 0, attached: 0 });
+
+        testing_env!(get_call_context("dan.testnet").attached_deposit(3 * 10u128.pow(24)).build());
+        contract.purchase_content("bob_near".parse().unwrap(), "date".to_owned());
+        let receipts = get_created_receipts();
+        assert_eq!(1, receipts.len());
+        assert_eq!("dan.testnet", receipts[0].receiver_id.as_str());
+        assert!(event_logs()[0].contains("\"event\":\"content_purchased\""));
+        assert_eq!(
+            U128::from(2 * 10u128.pow(24)),
+            contract.withdrawable_balance("bob_near".parse().unwrap())
+        );
+        assert!(contract.has_purchased(
+            "bob_near".parse().unwrap(),
+            "dan.testnet".parse().unwrap(),
+            "date".to_owned()
+        ));
+        assert_eq!(
+            "content test".to_owned(),
+            contract.get_content("bob_near".parse().unwrap(), "date".to_owned()).body
+        );
+
+        // only the purchased content is readable
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.get_content("bob_near".parse().unwrap(), "second date".to_owned());
+        }));
+        assert_contract_error(result, ContractError::NotSubscriber);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.purchase_content("bob_near".parse().unwrap(), "date".to_owned());
+        }));
+        assert_contract_error(result, ContractError::AlreadyPurchased);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.purchase_content("bob_near".parse().unwrap(), "second date".to_owned());
+        }));
+        assert_contract_error(result, ContractError::NotForSale);
+    }
+
+    #[test]
+    fn test_free_trial() {
+        testing_env!(get_context(false, "bob_near".parse().unwrap(), 10u128.pow(25)));
+        let mut contract = registered_contract(&["bob_near", "dan.testnet", "eve.testnet"]);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.set_free_trial(Some("0".to_owned()));
+        }));
+        assert_contract_error(result, ContractError::InvalidInput(String::new()));
+        contract.set_free_trial(Some("2".to_owned()));
+        assert_eq!(Some(2), contract.get_free_trial("bob_near".parse().unwrap()));
+
+        testing_env!(get_call_context("dan.testnet").build());
+        contract.subscribe("bob_near".parse().unwrap(), None, None);
+        assert!(event_logs()[0].contains("\"event\":\"trial_started\""));
+        assert!(!contract.is_trial_available("bob_near".parse().unwrap(), "dan.testnet".parse().unwrap()));
+        // the trial is kept apart from paid subscriptions
+        let profile = contract.internal_get_profile(&"bob_near".parse().unwrap()).unwrap();
+        assert_eq!(None, profile.subscribers.get(&"dan.testnet".parse().unwrap()));
+        assert_eq!(Some(3), profile.trials.get(&"dan.testnet".parse().unwrap()));
+
+        testing_env!(get_context(false, "bob_near".parse().unwrap(), 10u128.pow(25)));
+        contract.add_content("2".to_owned(), text_content("content test"));
+        contract.add_content("3".to_owned(), text_content("content test"));
+        testing_env!(get_context(false, "dan.testnet".parse().unwrap(), 10u128.pow(25)));
+        assert_eq!(
+            "content test".to_owned(),
+            contract.get_content("bob_near".parse().unwrap(), "3".to_owned()).body
+        );
+        testing_env!(get_context(false, "bob_near".parse().unwrap(), 10u128.pow(25)));
+        contract.add_content("4".to_owned(), text_content("content test"));
+        testing_env!(get_context(false, "dan.testnet".parse().unwrap(), 10u128.pow(25)));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.get_content("bob_near".parse().unwrap(), "4".to_owned());
+        }));
+        assert_contract_error(result, ContractError::SubscriptionExhausted);
+
+        // the trial can't be started again, subscribing needs a payment
+        testing_env!(get_call_context("dan.testnet").build());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.subscribe("bob_near".parse().unwrap(), None, None);
+        }));
+        assert_contract_error(result, ContractError::InsufficientDeposit { required: 0, attached: 0 });
+        testing_env!(get_call_context("dan.testnet").attached_deposit(10u128.pow(25)).build());
+        contract.subscribe("bob_near".parse().unwrap(), None, None);
+        assert_eq!(
+            "content test".to_owned(),
+            contract.get_content("bob_near".parse().unwrap(), "4".to_owned()).body
+        );
+
+        // accounts that have already paid for a subscription aren't new subscribers
+        testing_env!(get_call_context("eve.testnet").attached_deposit(10u128.pow(25)).build());
+        assert!(contract.is_trial_available("bob_near".parse().unwrap(), "eve.testnet".parse().unwrap()));
+        contract.subscribe("bob_near".parse().unwrap(), None, None);
+        assert!(!contract.is_trial_available("bob_near".parse().unwrap(), "eve.testnet".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_promo_codes() {
+        testing_env!(get_context(false, "bob_near".parse().unwrap(), 10u128.pow(25)));
+        let mut contract = registered_contract(&["bob_near", "dan.testnet", "carol.testnet"]);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.add_promo_code(
+                Base64VecU8::from(env::sha256(b"LAUNCH")),
+                Discount::Percent(101),
+                "1".to_owned(),
+                "100".to_owned()
+            );
+        }));
+        assert_contract_error(result, ContractError::InvalidInput(String::new()));
+        contract.add_promo_code(
+            Base64VecU8::from(env::sha256(b"LAUNCH")),
+            Discount::Percent(50),
+            "1".to_owned(),
+            "100".to_owned()
+        );
+        contract.add_promo_code(
+            Base64VecU8::from(env::sha256(b"EARLY")),
+            Discount::Fixed(U128::from(10u128.pow(24))),
+            "5".to_owned(),
+            "100".to_owned()
+        );
+        assert_eq!(None, contract.get_promo_code("bob_near".parse().unwrap(), "WRONG".to_owned()));
+
+        testing_env!(get_call_context("dan.testnet").attached_deposit(6 * 10u128.pow(24)).build());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.subscribe("bob_near".parse().unwrap(), None, Some("WRONG".to_owned()));
+        }));
+        assert_contract_error(result, ContractError::PromoCodeNotFound);
+        contract.subscribe("bob_near".parse().unwrap(), None, Some("LAUNCH".to_owned()));
+        let receipts = get_created_receipts();
+        assert_eq!(1, receipts.len());
+        assert_eq!("dan.testnet", receipts[0].receiver_id.as_str());
+        assert_eq!(
+            U128::from(5 * 10u128.pow(24)),
+            contract.withdrawable_balance("bob_near".parse().unwrap())
+        );
+        assert_eq!(
+            1,
+            contract.get_promo_code("bob_near".parse().unwrap(), "LAUNCH".to_owned()).unwrap().uses
+        );
+
+        testing_env!(get_call_context("carol.testnet").attached_deposit(10u128.pow(25)).build());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.subscribe("bob_near".parse().unwrap(), None, Some("LAUNCH".to_owned()));
+        }));
+        assert_contract_error(result, ContractError::PromoCodeUsedUp);
+
+        // the discounted price is what has to be covered
+        testing_env!(get_call_context("carol.testnet").attached_deposit(8 * 10u128.pow(24)).build());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.subscribe("bob_near".parse().unwrap(), None, Some("EARLY".to_owned()));
+        }));
+        assert_contract_error(result, ContractError::InsufficientDeposit { required: 0, attached: 0 });
+        assert_eq!(
+            0,
+            contract.get_promo_code("bob_near".parse().unwrap(), "EARLY".to_owned()).unwrap().uses
+        );
+
+        testing_env!(VMContextBuilder::new()
+            .signer_account_id("carol.testnet".parse().unwrap())
+            .predecessor_account_id("carol.testnet".parse().unwrap())
+            .attached_deposit(9 * 10u128.pow(24))
+            .block_index(101)
+            .build());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.subscribe("bob_near".parse().unwrap(), None, Some("EARLY".to_owned()));
+        }));
+        assert_contract_error(result, ContractError::PromoCodeExpired);
+
+        testing_env!(get_call_context("carol.testnet").attached_deposit(9 * 10u128.pow(24)).build());
+        contract.subscribe("bob_near".parse().unwrap(), None, Some("EARLY".to_owned()));
+        assert_eq!(
+            U128::from(14 * 10u128.pow(24)),
+            contract.withdrawable_balance("bob_near".parse().unwrap())
+        );
+
+        testing_env!(get_context(false, "bob_near".parse().unwrap(), 10u128.pow(25)));
+        contract.remove_promo_code(Base64VecU8::from(env::sha256(b"EARLY")));
+        assert_eq!(None, contract.get_promo_code("bob_near".parse().unwrap(), "EARLY".to_owned()));
+    }
+
+    #[test]
+    fn test_get_subscriptions() {
+        testing_env!(get_context(false, "creator_two".parse().unwrap(), 10u128.pow(25)));
+        let mut contract = registered_contract(&["bob_near", "creator_two", "dan.testnet"]);
+        contract.add_profile("creator_two".parse().unwrap(), "creator".to_owned(), "1".to_owned(), "2".to_owned());
+        contract.add_content("date".to_owned(), text_content("content from creator_two"));
+        assert_eq!(Vec::<SubscriptionView>::new(), contract.get_subscriptions("dan.testnet".parse().unwrap(), None, None));
+
+        testing_env!(get_call_context("dan.testnet").attached_deposit(10u128.pow(25)).build());
+        contract.subscribe("bob_near".parse().unwrap(), None, None);
+        contract.subscribe("creator_two".parse().unwrap(), None, None);
+        testing_env!(get_context(false, "bob_near".parse().unwrap(), 10u128.pow(25)));
+        contract.add_content("second date".to_owned(), text_content("second post"));
+
+        assert_eq!(
+            vec![
+                SubscriptionView {
+                    creator: "bob_near".parse().unwrap(),
+                    remaining_credit: Some(3),
+                    expires_at: None
+                },
+                SubscriptionView {
+                    creator: "creator_two".parse().unwrap(),
+                    remaining_credit: Some(2),
+                    expires_at: None
+                }
+            ],
+            contract.get_subscriptions("dan.testnet".parse().unwrap(), None, None)
+        );
+        assert_eq!(
+            vec!["creator_two".parse::<AccountId>().unwrap()],
+            contract
+                .get_subscriptions("dan.testnet".parse().unwrap(), Some(1), Some(1))
+                .into_iter()
+                .map(|subscription| subscription.creator)
+                .collect::<Vec<_>>()
+        );
+        assert!(contract.get_subscriptions("bob_near".parse().unwrap(), None, None).is_empty());
+
+        // a transferred pass moves the subscription to the receiver
+        testing_env!(get_call_context("dan.testnet").attached_deposit(1).build());
+        contract.nft_transfer("carol.testnet".parse().unwrap(), "1".to_owned(), None, None);
+        assert_eq!(
+            vec!["creator_two".parse::<AccountId>().unwrap()],
+            contract
+                .get_subscriptions("dan.testnet".parse().unwrap(), None, None)
+                .into_iter()
+                .map(|subscription| subscription.creator)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![SubscriptionView {
+                creator: "bob_near".parse().unwrap(),
+                remaining_credit: Some(3),
+                expires_at: None
+            }],
+            contract.get_subscriptions("carol.testnet".parse().unwrap(), None, None)
+        );
+    }
+}
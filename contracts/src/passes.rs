@@ -0,0 +1,211 @@
+use crate::*;
+use near_contract_standards::{
+    impl_non_fungible_token_enumeration,
+    non_fungible_token::{
+        core::{NonFungibleTokenCore, NonFungibleTokenResolver},
+        events::NftMint,
+        metadata::{NFTContractMetadata, NonFungibleTokenMetadataProvider, TokenMetadata, NFT_METADATA_SPEC},
+        Token
+    }
+};
+use near_sdk::{
+    serde_json,
+    PromiseOrValue
+};
+use std::collections::HashMap;
+
+impl Contract {
+    /// Mints `owner_id` a pass for the subscription described by `pass`
+    pub(crate) fn internal_mint_pass(&mut self, owner_id: &AccountId, pass: Pass) -> TokenId {
+        let token_id = (self.passes.owner_by_id.len() + 1).to_string();
+        let metadata = TokenMetadata {
+            title: Some(format!("Subscription to {}", pass.creator)),
+            description: None,
+            media: None,
+            media_hash: None,
+            copies: None,
+            issued_at: None,
+            expires_at: None,
+            starts_at: None,
+            updated_at: None,
+            extra: Some(serde_json::to_string(&pass).unwrap()),
+            reference: None,
+            reference_hash: None
+        };
+        self.passes.internal_mint_with_refund(token_id.clone(), owner_id.clone(), Some(metadata), None);
+        NftMint {
+            owner_id,
+            token_ids: &[&token_id],
+            memo: None
+        }.emit();
+        self.pass_terms.insert(&token_id, &pass);
+        token_id
+    }
+
+    /// Returns the passes `owner_id` holds for `creator_address` that haven't been refunded
+    pub(crate) fn internal_get_passes(&self, owner_id: &AccountId, creator_address: &AccountId) -> Vec<(TokenId, Pass)> {
+        let token_ids = match self.passes.tokens_per_owner.as_ref().and_then(|by_owner| by_owner.get(owner_id)) {
+            Some(token_ids) => token_ids,
+            None => return Vec::new()
+        };
+        token_ids
+            .iter()
+            .filter_map(|token_id| self.pass_terms.get(&token_id).map(|pass| (token_id, pass)))
+            .filter(|(_, pass)| &pass.creator == creator_address)
+            .collect()
+    }
+
+    /// Stops `token_id` giving access, the token itself stays with its owner
+    pub(crate) fn internal_end_pass(&mut self, token_id: &TokenId) {
+        self.pass_terms.remove(token_id);
+    }
+
+    /// Ends the own subscription record of `owner_id` if it is the one `token_id`
+    /// was minted for, so access leaves with the pass, and lists the subscription under `receiver_id`
+    fn internal_release_pass(&mut self, token_id: &TokenId, owner_id: &AccountId, receiver_id: &AccountId) {
+        let pass = match self.pass_terms.get(token_id) {
+            Some(pass) => pass,
+            None => return
+        };
+        let mut profile = match self.internal_get_profile(&pass.creator) {
+            Some(profile) => profile,
+            None => return
+        };
+        self.internal_add_subscription(receiver_id, &pass.creator);
+        if profile.is_pass_subscription(owner_id, &pass) {
+            profile.end_subscription(owner_id);
+            self.internal_set_profile(&pass.creator, profile);
+            let holds_other_passes = self
+                .internal_get_passes(owner_id, &pass.creator)
+                .iter()
+                .any(|(other_token_id, _)| other_token_id != token_id);
+            if !holds_other_passes {
+                self.internal_remove_subscription(owner_id, &pass.creator);
+            }
+        }
+    }
+
+    /// Takes the refund record of the subscription `token_id` was minted for from its
+    /// owner while the pass is sent with `nft_transfer_call`, so they can't be refunded
+    /// for a pass the receiver may keep
+    fn internal_hold_payment(&mut self, token_id: &TokenId) {
+        let (owner_id, pass) = match (self.passes.owner_by_id.get(token_id), self.pass_terms.get(token_id)) {
+            (Some(owner_id), Some(pass)) => (owner_id, pass),
+            _ => return
+        };
+        let mut profile = match self.internal_get_profile(&pass.creator) {
+            Some(profile) => profile,
+            None => return
+        };
+        if !profile.is_pass_subscription(&owner_id, &pass) {
+            return;
+        }
+        if let Some(payment) = profile.payments.remove(&owner_id) {
+            self.internal_set_profile(&pass.creator, profile);
+            self.held_payments.insert(token_id, &payment);
+        }
+    }
+
+    /// Gives `owner_id` back the refund record held while `token_id` was sent to a receiver that returned it
+    fn internal_restore_payment(&mut self, token_id: &TokenId, owner_id: &AccountId, payment: Payment) {
+        let pass = match self.pass_terms.get(token_id) {
+            Some(pass) => pass,
+            None => return
+        };
+        let mut profile = match self.internal_get_profile(&pass.creator) {
+            Some(profile) => profile,
+            None => return
+        };
+        if profile.is_pass_subscription(owner_id, &pass) {
+            profile.payments.insert(owner_id, &payment);
+            self.internal_set_profile(&pass.creator, profile);
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Returns the subscription `token_id` was minted for, `None` once it has been refunded
+    pub fn get_pass(&self, token_id: TokenId) -> Option<Pass> {
+        self.pass_terms.get(&token_id)
+    }
+}
+
+#[near_bindgen]
+impl NonFungibleTokenCore for Contract {
+    /// Transfers a pass and the access it gives, the sender can't renew or be refunded for it afterwards.
+    /// The contract pays for any storage the transfer takes up, so neither side has to be registered
+    #[payable]
+    fn nft_transfer(&mut self, receiver_id: AccountId, token_id: TokenId, approval_id: Option<u64>, memo: Option<String>) {
+        if let Some(owner_id) = self.passes.owner_by_id.get(&token_id) {
+            self.internal_release_pass(&token_id, &owner_id, &receiver_id);
+        }
+        self.passes.nft_transfer(receiver_id, token_id, approval_id, memo);
+    }
+
+    /// Transfers a pass like `nft_transfer` and calls `nft_on_transfer` on the receiver
+    ///
+    /// The sender keeps their subscription until the receiver keeps the pass, only its
+    /// refund record is held back in the meantime and given back if the pass is returned
+    #[payable]
+    fn nft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        msg: String
+    ) -> PromiseOrValue<bool> {
+        self.internal_hold_payment(&token_id);
+        self.passes.nft_transfer_call(receiver_id, token_id, approval_id, memo, msg)
+    }
+
+    fn nft_token(&self, token_id: TokenId) -> Option<Token> {
+        self.passes.nft_token(token_id)
+    }
+}
+
+#[near_bindgen]
+impl NonFungibleTokenResolver for Contract {
+    /// Moves the subscription to the receiver if they kept the pass,
+    /// otherwise gives the sender back its refund record
+    #[private]
+    fn nft_resolve_transfer(
+        &mut self,
+        previous_owner_id: AccountId,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approved_account_ids: Option<HashMap<AccountId, u64>>
+    ) -> bool {
+        let transferred = self.passes.nft_resolve_transfer(
+            previous_owner_id.clone(),
+            receiver_id.clone(),
+            token_id.clone(),
+            approved_account_ids
+        );
+        let held_payment = self.held_payments.remove(&token_id);
+        if transferred {
+            self.internal_release_pass(&token_id, &previous_owner_id, &receiver_id);
+        } else if let Some(payment) = held_payment {
+            self.internal_restore_payment(&token_id, &previous_owner_id, payment);
+        }
+        transferred
+    }
+}
+
+impl_non_fungible_token_enumeration!(Contract, passes);
+
+#[near_bindgen]
+impl NonFungibleTokenMetadataProvider for Contract {
+    fn nft_metadata(&self) -> NFTContractMetadata {
+        NFTContractMetadata {
+            spec: NFT_METADATA_SPEC.to_owned(),
+            name: "Content Subscription Pass".to_owned(),
+            symbol: "PASS".to_owned(),
+            icon: None,
+            base_uri: None,
+            reference: None,
+            reference_hash: None
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use crate::*;
+use near_sdk::json_types::Base64VecU8;
+
+#[near_bindgen]
+impl Contract {
+    /// Adds a promo code to the callers profile that takes `discount` off a subscription
+    ///
+    /// Only the sha256 hash of the code is stored, passed as `code_hash`. It can be used
+    /// `max_uses` times in total, until block `expires_at_block`
+    pub fn add_promo_code(&mut self, code_hash: Base64VecU8, discount: Discount, max_uses: String, expires_at_block: String) {
+        let promo_code = PromoCode {
+            discount,
+            max_uses: parse_input::<u32>(&max_uses, "max_uses"),
+            uses: 0,
+            expires_at_block: parse_input::<u64>(&expires_at_block, "expires_at_block")
+        };
+        self.internal_update_own_profile(|profile| profile.add_promo_code(code_hash.into(), promo_code));
+    }
+
+    pub fn remove_promo_code(&mut self, code_hash: Base64VecU8) {
+        self.internal_update_own_profile(|profile| profile.remove_promo_code(code_hash.into()));
+    }
+
+    /// Returns the discount and uses of `code` on the profile of `creator_address`, `None` if it doesn't exist
+    pub fn get_promo_code(&self, creator_address: AccountId, code: String) -> Option<PromoCode> {
+        match self.internal_get_profile(&creator_address) {
+            Some(profile) => profile.promo_codes.get(&env::sha256(code.as_bytes())),
+            None => ContractError::ProfileNotFound.panic()
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    /// Lets anyone buy permanent access to the callers content under `content_id`
+    /// for `price` yoctoNEAR, `None` stops it being sold on its own
+    pub fn set_content_price(&mut self, content_id: String, price: Option<U128>) {
+        self.internal_update_own_profile(|profile| profile.set_content_price(&content_id, price));
+    }
+
+    /// yoctoNEAR it costs to buy the content under `content_id` on its own, `None` if it isn't for sale
+    pub fn get_content_price(&self, creator_address: AccountId, content_id: String) -> Option<U128> {
+        match self.internal_get_profile(&creator_address) {
+            Some(profile) => profile.get_content_price(&content_id),
+            None => ContractError::ProfileNotFound.panic()
+        }
+    }
+
+    /// Buys the signer permanent access to the content of `creator_address` under `content_id`
+    ///
+    /// The deposit must cover the price, anything above it is refunded. The payment
+    /// is held for the creator like a subscription payment
+    #[payable]
+    pub fn purchase_content(&mut self, creator_address: AccountId, content_id: String) {
+        self.assert_not_paused();
+        let buyer = env::signer_account_id();
+        let mut profile = match self.internal_get_profile(&creator_address) {
+            Some(profile) => profile,
+            None => ContractError::ProfileNotFound.panic()
+        };
+        let price = match profile.get_content_price(&content_id) {
+            Some(price) => price,
+            None => ContractError::NotForSale.panic()
+        };
+        let deposit = env::attached_deposit();
+        if deposit < price.0 {
+            ContractError::InsufficientDeposit {
+                required: price.0,
+                attached: deposit
+            }.panic();
+        }
+        let initial_storage_usage = env::storage_usage();
+        profile.purchase(&buyer, content_id.clone());
+        let creator_amount = price.0 - self.internal_collect_fee(price.0);
+        let split = profile.split_payment(&creator_address, creator_amount);
+        self.internal_set_profile(&creator_address, profile);
+        self.internal_credit_split(split);
+        self.charge_storage(&buyer, initial_storage_usage);
+        Event::ContentPurchased(vec![ContentPurchasedData {
+            creator: creator_address,
+            buyer,
+            content_id,
+            amount: price
+        }]).emit();
+        let refund = deposit - price.0;
+        if refund > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+    }
+
+    pub fn has_purchased(&self, creator_address: AccountId, account_id: AccountId, content_id: String) -> bool {
+        match self.internal_get_profile(&creator_address) {
+            Some(profile) => profile.purchases.contains(&(account_id, content_id)),
+            None => ContractError::ProfileNotFound.panic()
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use crate::*;
+use near_sdk::{
+    assert_one_yocto,
+    serde_json::json,
+    Balance,
+    Gas,
+    PromiseResult
+};
+
+const GAS_FOR_ON_WITHDRAW: Gas = Gas(10_000_000_000_000);
+
+impl Contract {
+    /// Adds a subscription payment of `amount` yoctoNEAR to the balance of `creator_address`
+    pub(crate) fn internal_credit_creator(&mut self, creator_address: &AccountId, amount: Balance) {
+        let mut creator_balance = self.creator_balances.get(creator_address).unwrap_or_default();
+        creator_balance.balance += amount;
+        self.creator_balances.insert(creator_address, &creator_balance);
+    }
+
+    /// Moves the platform fee on a payment of `amount` yoctoNEAR into the treasury, returning the fee
+    pub(crate) fn internal_collect_fee(&mut self, amount: Balance) -> Balance {
+        let fee = amount * self.platform_fee_bps as Balance / TOTAL_SHARE_BPS as Balance;
+        self.treasury.balance += fee;
+        self.fees_collected += fee;
+        fee
+    }
+
+    /// Credits each account its share of a payment split by `Profile::split_payment`
+    pub(crate) fn internal_credit_split(&mut self, split: Vec<(AccountId, Balance)>) {
+        for (account_id, amount) in split {
+            self.internal_credit_creator(&account_id, amount);
+        }
+    }
+
+    /// Takes each accounts share of a split refund back out of their withdrawable
+    /// balance, returning the total taken
+    ///
+    /// Panics with `ContractError::RefundUnavailable` if any of them has already
+    /// withdrawn their share, so a refund is never paid in part
+    pub(crate) fn internal_debit_split(&mut self, split: Vec<(AccountId, Balance)>) -> Balance {
+        let owed: Balance = split.iter().map(|(_, amount)| amount).sum();
+        let debited: Balance = split
+            .into_iter()
+            .map(|(account_id, amount)| self.internal_debit_creator(&account_id, amount))
+            .sum();
+        if debited < owed {
+            ContractError::RefundUnavailable {
+                owed,
+                available: debited
+            }.panic();
+        }
+        debited
+    }
+
+    /// Takes up to `amount` yoctoNEAR out of the withdrawable balance of `creator_address`,
+    /// returning how much was taken
+    pub(crate) fn internal_debit_creator(&mut self, creator_address: &AccountId, amount: Balance) -> Balance {
+        let mut creator_balance = self.creator_balances.get(creator_address).unwrap_or_default();
+        let debited = std::cmp::min(amount, creator_balance.withdrawable());
+        creator_balance.balance -= debited;
+        self.creator_balances.insert(creator_address, &creator_balance);
+        debited
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// yoctoNEAR of subscription payments `creator_address` can withdraw now
+    pub fn withdrawable_balance(&self, creator_address: AccountId) -> U128 {
+        U128::from(
+            self.creator_balances
+                .get(&creator_address)
+                .map(|creator_balance| creator_balance.withdrawable())
+                .unwrap_or(0)
+        )
+    }
+
+    /// Sends `amount` yoctoNEAR of the callers subscription payments to them
+    ///
+    /// The amount is held as pending until the transfer resolves, it only leaves
+    /// the balance in `on_withdraw` once the transfer succeeded
+    #[payable]
+    pub fn withdraw(&mut self, amount: U128) -> Promise {
+        assert_one_yocto();
+        let creator_address = env::predecessor_account_id();
+        let mut creator_balance = self.creator_balances.get(&creator_address).unwrap_or_default();
+        if amount.0 == 0 || amount.0 > creator_balance.withdrawable() {
+            ContractError::InsufficientBalance {
+                required: amount.0,
+                available: creator_balance.withdrawable()
+            }.panic();
+        }
+        creator_balance.pending += amount.0;
+        self.creator_balances.insert(&creator_address, &creator_balance);
+        Promise::new(creator_address.clone())
+            .transfer(amount.0)
+            .then(Promise::new(env::current_account_id()).function_call(
+                "on_withdraw".to_owned(),
+                json!({
+                    "creator_address": creator_address,
+                    "amount": amount
+                }).to_string().into_bytes(),
+                0,
+                GAS_FOR_ON_WITHDRAW
+            ))
+    }
+
+    /// Sets the basis points of each subscription payment kept as a platform fee,
+    /// only the owner can call this
+    ///
+    /// Fees on token payments are held per token and withdrawn with `withdraw_token_fees`
+    pub fn set_platform_fee(&mut self, fee_bps: u16) {
+        self.assert_owner();
+        if fee_bps > TOTAL_SHARE_BPS {
+            ContractError::InvalidInput(format!("the fee can't be above {} basis points", TOTAL_SHARE_BPS)).panic();
+        }
+        self.platform_fee_bps = fee_bps;
+    }
+
+    pub fn get_platform_fee(&self) -> PlatformFeeView {
+        PlatformFeeView {
+            fee_bps: self.platform_fee_bps,
+            treasury_balance: U128::from(self.treasury.withdrawable()),
+            fees_collected: U128::from(self.fees_collected)
+        }
+    }
+
+    /// Sends `amount` yoctoNEAR of collected platform fees to `receiver_id`, the owner
+    /// when omitted, only the owner can call this
+    ///
+    /// Held as pending until the transfer resolves, like a creators `withdraw`. A
+    /// factory owned contract can't spend the fees itself, so it names the receiver
+    #[payable]
+    pub fn withdraw_fees(&mut self, amount: U128, receiver_id: Option<AccountId>) -> Promise {
+        assert_one_yocto();
+        self.assert_owner();
+        if amount.0 == 0 || amount.0 > self.treasury.withdrawable() {
+            ContractError::InsufficientBalance {
+                required: amount.0,
+                available: self.treasury.withdrawable()
+            }.panic();
+        }
+        let receiver_id = receiver_id.unwrap_or_else(|| self.owner_id.clone());
+        self.treasury.pending += amount.0;
+        Promise::new(receiver_id.clone())
+            .transfer(amount.0)
+            .then(Promise::new(env::current_account_id()).function_call(
+                "on_withdraw_fees".to_owned(),
+                json!({
+                    "receiver_id": receiver_id,
+                    "amount": amount
+                }).to_string().into_bytes(),
+                0,
+                GAS_FOR_ON_WITHDRAW
+            ))
+    }
+
+    /// Commits the caller to publishing at least every `max_post_gap_ns` nanoseconds
+    /// or every `max_post_gap_blocks` blocks, passing neither removes the commitment
+    ///
+    /// Applies to payments made from now on, earlier payments keep the commitment
+    /// they were made under
+    pub fn set_max_post_gap(&mut self, max_post_gap_ns: Option<String>, max_post_gap_blocks: Option<String>) {
+        let max_post_gap = match (max_post_gap_ns, max_post_gap_blocks) {
+            (Some(gap), None) => Some(PostGap::Nanoseconds(parse_input(&gap, "max_post_gap_ns"))),
+            (None, Some(gap)) => Some(PostGap::Blocks(parse_input(&gap, "max_post_gap_blocks"))),
+            (None, None) => None,
+            (Some(_), Some(_)) => ContractError::InvalidInput(
+                "give the gap between posts in nanoseconds or in blocks, not both".to_owned()
+            ).panic()
+        };
+        self.internal_update_own_profile(|profile| profile.set_max_post_gap(max_post_gap));
+    }
+
+    /// Splits the callers future subscription payments between `payees`, whose
+    /// `share_bps` must add up to 10000 basis points
+    ///
+    /// Each payee withdraws their own share, an empty list sends everything to the creator again
+    pub fn set_revenue_split(&mut self, payees: Vec<Payee>) {
+        self.internal_update_own_profile(|profile| profile.set_payees(payees));
+    }
+
+    /// Lists the payees of `creator_address`, empty when the creator gets every payment
+    pub fn get_revenue_split(&self, creator_address: AccountId) -> Vec<Payee> {
+        match self.internal_get_profile(&creator_address) {
+            Some(profile) => profile.payees,
+            None => ContractError::ProfileNotFound.panic()
+        }
+    }
+
+    pub fn get_max_post_gap(&self, creator_address: AccountId) -> Option<PostGap> {
+        match self.internal_get_profile(&creator_address) {
+            Some(profile) => profile.max_post_gap,
+            None => ContractError::ProfileNotFound.panic()
+        }
+    }
+
+    /// Refunds the caller the unused share of their last NEAR payment to `creator_address`
+    /// once the creator has gone longer than they committed to without publishing
+    ///
+    /// The subscription and the pass minted for it end with the refund. It is paid
+    /// back out of the balances it was split between, if they have withdrawn
+    /// too much of it the call fails and the subscription carries on. The platform
+    /// fee isn't refunded, and token payments are final
+    pub fn claim_refund(&mut self, creator_address: AccountId) -> U128 {
+        let subscriber_address = env::predecessor_account_id();
+        let mut profile = match self.internal_get_profile(&creator_address) {
+            Some(profile) => profile,
+            None => ContractError::ProfileNotFound.panic()
+        };
+        let refunded_passes: Vec<TokenId> = self.internal_get_passes(&subscriber_address, &creator_address)
+            .into_iter()
+            .filter(|(_, pass)| profile.is_pass_subscription(&subscriber_address, pass))
+            .map(|(token_id, _)| token_id)
+            .collect();
+        let (owed, payees) = match profile.refund(&subscriber_address) {
+            Ok(refund) => refund,
+            Err(error) => error.panic()
+        };
+        let split = split_between(&payees, &creator_address, owed);
+        let initial_storage_usage = env::storage_usage();
+        self.internal_set_profile(&creator_address, profile);
+        for token_id in refunded_passes {
+            self.internal_end_pass(&token_id);
+        }
+        self.charge_storage(&subscriber_address, initial_storage_usage);
+        let refund = self.internal_debit_split(split);
+        if refund > 0 {
+            Promise::new(subscriber_address.clone()).transfer(refund);
+        }
+        Event::Refunded(vec![RefundedData {
+            creator: creator_address,
+            subscriber: subscriber_address,
+            amount: U128::from(refund)
+        }]).emit();
+        U128::from(refund)
+    }
+
+    /// Takes a withdrawal out of the creators balance if its transfer succeeded,
+    /// otherwise makes it withdrawable again
+    #[private]
+    pub fn on_withdraw(&mut self, creator_address: AccountId, amount: U128) -> bool {
+        let mut creator_balance = match self.creator_balances.get(&creator_address) {
+            Some(creator_balance) => creator_balance,
+            None => ContractError::InvalidState("withdrawal without a creator balance".to_owned()).panic()
+        };
+        creator_balance.pending -= amount.0;
+        let transferred = matches!(env::promise_result(0), PromiseResult::Successful(_));
+        if transferred {
+            creator_balance.balance -= amount.0;
+            Event::Withdrawn(vec![WithdrawnData {
+                creator: creator_address.clone(),
+                amount,
+                token_id: None
+            }]).emit();
+        }
+        self.creator_balances.insert(&creator_address, &creator_balance);
+        transferred
+    }
+
+    /// Takes a fee withdrawal out of the treasury if its transfer succeeded,
+    /// otherwise makes it withdrawable again
+    #[private]
+    pub fn on_withdraw_fees(&mut self, receiver_id: AccountId, amount: U128) -> bool {
+        self.treasury.pending -= amount.0;
+        let transferred = matches!(env::promise_result(0), PromiseResult::Successful(_));
+        if transferred {
+            self.treasury.balance -= amount.0;
+            Event::FeesWithdrawn(vec![FeesWithdrawnData {
+                receiver: receiver_id,
+                amount,
+                token_id: None
+            }]).emit();
+        }
+        transferred
+    }
+}
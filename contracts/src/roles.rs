@@ -0,0 +1,66 @@
+use crate::*;
+
+impl Contract {
+    pub(crate) fn assert_owner(&self) {
+        if env::predecessor_account_id() != self.owner_id {
+            ContractError::NotOwner.panic();
+        }
+    }
+
+    /// Panics unless the caller is the owner or an admin
+    pub(crate) fn assert_admin(&self) {
+        let account_id = env::predecessor_account_id();
+        if account_id != self.owner_id && !self.admins.contains(&account_id) {
+            ContractError::NotAdmin.panic();
+        }
+    }
+
+    pub(crate) fn assert_not_paused(&self) {
+        if self.paused {
+            ContractError::Paused.panic();
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn get_owner(&self) -> AccountId {
+        self.owner_id.clone()
+    }
+
+    /// Hands the contract over to `owner_id`, only the current owner can call this
+    pub fn set_owner(&mut self, owner_id: AccountId) {
+        self.assert_owner();
+        self.owner_id = owner_id;
+    }
+
+    pub fn add_admin(&mut self, account_id: AccountId) {
+        self.assert_owner();
+        self.admins.insert(&account_id);
+    }
+
+    pub fn remove_admin(&mut self, account_id: AccountId) {
+        self.assert_owner();
+        self.admins.remove(&account_id);
+    }
+
+    pub fn get_admins(&self) -> Vec<AccountId> {
+        self.admins.to_vec()
+    }
+
+    /// Stops new profiles, content and subscriptions until `unpause` is called,
+    /// everything can still be read while paused
+    pub fn pause(&mut self) {
+        self.assert_admin();
+        self.paused = true;
+    }
+
+    pub fn unpause(&mut self) {
+        self.assert_admin();
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+}
@@ -0,0 +1,174 @@
+use crate::*;
+use near_contract_standards::storage_management::{
+    StorageBalance,
+    StorageBalanceBounds,
+    StorageManagement
+};
+use near_sdk::{
+    assert_one_yocto,
+    Balance,
+    StorageUsage
+};
+
+/// Bytes taken by a storage registration for the longest possible account id
+///
+/// 64 byte account id + 4 byte length + 1 byte prefix for the key,
+/// 24 bytes for the `StorageAccount` value and 40 bytes of trie overhead
+pub const STORAGE_REGISTRATION_BYTES: StorageUsage = 133;
+
+impl Contract {
+    /// Charges `account_id` for any storage used since `initial_storage_usage`
+    ///
+    /// Panics if the account isn't registered or can't cover the new bytes,
+    /// storage that was freed is credited back to the account. The creator of a
+    /// single creator contract is never charged, the account balance they
+    /// funded covers their storage
+    pub(crate) fn charge_storage(&mut self, account_id: &AccountId, initial_storage_usage: StorageUsage) {
+        if self.creator_id.as_ref() == Some(account_id) {
+            return;
+        }
+        let mut storage_account = match self.storage_accounts.get(account_id) {
+            Some(storage_account) => storage_account,
+            None => ContractError::NotRegistered.panic()
+        };
+        let current_storage_usage = env::storage_usage();
+        if current_storage_usage >= initial_storage_usage {
+            storage_account.bytes_used += current_storage_usage - initial_storage_usage;
+        } else {
+            storage_account.bytes_used = storage_account.bytes_used.saturating_sub(
+                initial_storage_usage - current_storage_usage
+            );
+        }
+        if storage_account.locked() > storage_account.deposit {
+            ContractError::InsufficientStorageBalance {
+                required: storage_account.locked(),
+                available: storage_account.deposit
+            }.panic();
+        }
+        self.storage_accounts.insert(account_id, &storage_account);
+    }
+
+    fn storage_balance(storage_account: &StorageAccount) -> StorageBalance {
+        StorageBalance {
+            total: U128::from(storage_account.deposit),
+            available: U128::from(storage_account.available())
+        }
+    }
+
+    fn storage_minimum_balance() -> Balance {
+        Balance::from(STORAGE_REGISTRATION_BYTES) * env::storage_byte_cost()
+    }
+}
+
+#[near_bindgen]
+impl StorageManagement for Contract {
+    #[payable]
+    fn storage_deposit(&mut self, account_id: Option<AccountId>, registration_only: Option<bool>) -> StorageBalance {
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let registration_only = registration_only.unwrap_or(false);
+        let deposit = env::attached_deposit();
+        let storage_account = match self.storage_accounts.get(&account_id) {
+            Some(mut storage_account) => {
+                if registration_only {
+                    if deposit > 0 {
+                        Promise::new(env::predecessor_account_id()).transfer(deposit);
+                    }
+                } else {
+                    storage_account.deposit += deposit;
+                }
+                storage_account
+            },
+            None => {
+                let minimum_balance = Self::storage_minimum_balance();
+                if deposit < minimum_balance {
+                    ContractError::InsufficientDeposit {
+                        required: minimum_balance,
+                        attached: deposit
+                    }.panic();
+                }
+                let deposit = if registration_only {
+                    let refund = deposit - minimum_balance;
+                    if refund > 0 {
+                        Promise::new(env::predecessor_account_id()).transfer(refund);
+                    }
+                    minimum_balance
+                } else {
+                    deposit
+                };
+                StorageAccount {
+                    deposit,
+                    bytes_used: STORAGE_REGISTRATION_BYTES
+                }
+            }
+        };
+        self.storage_accounts.insert(&account_id, &storage_account);
+        Self::storage_balance(&storage_account)
+    }
+
+    #[payable]
+    fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let mut storage_account = match self.storage_accounts.get(&account_id) {
+            Some(storage_account) => storage_account,
+            None => ContractError::NotRegistered.panic()
+        };
+        let available = storage_account.available();
+        let amount = amount.map(|amount| amount.0).unwrap_or(available);
+        if amount > available {
+            ContractError::InsufficientStorageBalance {
+                required: amount,
+                available
+            }.panic();
+        }
+        storage_account.deposit -= amount;
+        self.storage_accounts.insert(&account_id, &storage_account);
+        if amount > 0 {
+            Promise::new(account_id).transfer(amount);
+        }
+        Self::storage_balance(&storage_account)
+    }
+
+    /// Unregisters the caller and refunds their available storage balance
+    ///
+    /// Without `force` this panics if the caller still has data stored. With
+    /// `force` the callers profile is removed and the balance locked for its
+    /// remaining collection entries is kept by the contract. A profile they add
+    /// later gets new collections, so none of the old content or subscribers come back
+    #[payable]
+    fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let storage_account = match self.storage_accounts.get(&account_id) {
+            Some(storage_account) => storage_account,
+            None => return false
+        };
+        if storage_account.bytes_used > STORAGE_REGISTRATION_BYTES {
+            if !force.unwrap_or(false) {
+                ContractError::StorageInUse.panic();
+            }
+            self.internal_remove_profile(&account_id);
+        }
+        self.storage_accounts.remove(&account_id);
+        let refund = storage_account.available()
+            + Balance::from(STORAGE_REGISTRATION_BYTES) * env::storage_byte_cost();
+        let refund = std::cmp::min(refund, storage_account.deposit);
+        if refund > 0 {
+            Promise::new(account_id).transfer(refund);
+        }
+        true
+    }
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        StorageBalanceBounds {
+            min: U128::from(Self::storage_minimum_balance()),
+            max: None
+        }
+    }
+
+    fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.storage_accounts
+            .get(&account_id)
+            .map(|storage_account| Self::storage_balance(&storage_account))
+    }
+}
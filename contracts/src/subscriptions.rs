@@ -0,0 +1,63 @@
+use crate::*;
+
+impl Contract {
+    /// Records that `subscriber_address` has subscribed to `creator_address`
+    pub(crate) fn internal_add_subscription(&mut self, subscriber_address: &AccountId, creator_address: &AccountId) {
+        let mut creators = self.subscriptions.get(subscriber_address).unwrap_or_else(|| {
+            UnorderedSet::new(StorageKeys::SubscriptionCreators { account_hash: account_hash(subscriber_address) })
+        });
+        if creators.insert(creator_address) {
+            self.subscriptions.insert(subscriber_address, &creators);
+        }
+    }
+
+    /// Drops `creator_address` from the subscriptions of `subscriber_address`
+    pub(crate) fn internal_remove_subscription(&mut self, subscriber_address: &AccountId, creator_address: &AccountId) {
+        let mut creators = match self.subscriptions.get(subscriber_address) {
+            Some(creators) => creators,
+            None => return
+        };
+        if !creators.remove(creator_address) {
+            return;
+        }
+        if creators.is_empty() {
+            self.subscriptions.remove(subscriber_address);
+        } else {
+            self.subscriptions.insert(subscriber_address, &creators);
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Lists the creators `account_id` has subscribed to or holds a pass for, with what is left
+    /// of the best of their subscription and passes for each
+    ///
+    /// Starts at `from_index` (default 0) and returns at most `limit` (default 10) items.
+    /// Creators whose profile has since been removed are left out
+    pub fn get_subscriptions(&self, account_id: AccountId, from_index: Option<u64>, limit: Option<u64>) -> Vec<SubscriptionView> {
+        let creators = match self.subscriptions.get(&account_id) {
+            Some(creators) => creators,
+            None => return Vec::new()
+        };
+        creators
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(10) as usize)
+            .filter_map(|creator_address| {
+                let profile = self.internal_get_profile(&creator_address)?;
+                let passes = self.internal_get_passes(&account_id, &creator_address);
+                profile
+                    .get_subscription(&creator_address, &account_id)
+                    .into_iter()
+                    .chain(
+                        passes
+                            .iter()
+                            .filter(|(_, pass)| profile.is_own_pass(pass))
+                            .map(|(_, pass)| profile.get_pass_subscription(pass))
+                    )
+                    .max_by_key(|subscription| (subscription.remaining_credit, subscription.expires_at))
+            })
+            .collect()
+    }
+}
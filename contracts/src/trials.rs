@@ -0,0 +1,52 @@
+use crate::*;
+
+impl Contract {
+    pub(crate) fn internal_is_trial_available(&self, creator_address: &AccountId, subscriber_address: &AccountId) -> bool {
+        match self.internal_get_profile(creator_address) {
+            Some(profile) => profile.is_trial_available(subscriber_address),
+            None => false
+        }
+    }
+
+    /// Starts the free trial of `subscriber_address`, who pays for its storage
+    pub(crate) fn internal_start_trial(&mut self, creator_address: AccountId, subscriber_address: AccountId) {
+        self.assert_not_paused();
+        let mut profile = match self.internal_get_profile(&creator_address) {
+            Some(profile) => profile,
+            None => ContractError::ProfileNotFound.panic()
+        };
+        profile.assert_subscription_ended(&subscriber_address);
+        let initial_storage_usage = env::storage_usage();
+        let content_count = profile.start_trial(&subscriber_address);
+        self.internal_set_profile(&creator_address, profile);
+        self.charge_storage(&subscriber_address, initial_storage_usage);
+        Event::TrialStarted(vec![TrialStartedData {
+            creator: creator_address,
+            subscriber: subscriber_address,
+            content_count
+        }]).emit();
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Offers each new subscriber `trial_posts` pieces of the callers content for free,
+    /// once per account. `None` stops offering a trial
+    pub fn set_free_trial(&mut self, trial_posts: Option<String>) {
+        let trial_posts = trial_posts.map(|posts| parse_input::<i32>(&posts, "trial_posts"));
+        self.internal_update_own_profile(|profile| profile.set_trial_posts(trial_posts));
+    }
+
+    /// Pieces of content the free trial of `creator_address` covers, `None` if they don't offer one
+    pub fn get_free_trial(&self, creator_address: AccountId) -> Option<i32> {
+        match self.internal_get_profile(&creator_address) {
+            Some(profile) => profile.trial_posts,
+            None => ContractError::ProfileNotFound.panic()
+        }
+    }
+
+    /// Returns whether `account_id` can still start the free trial of `creator_address`
+    pub fn is_trial_available(&self, creator_address: AccountId, account_id: AccountId) -> bool {
+        self.internal_is_trial_available(&creator_address, &account_id)
+    }
+}
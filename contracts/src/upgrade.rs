@@ -0,0 +1,231 @@
+use crate::*;
+use near_sdk::{
+    collections::{LookupSet, UnorderedMap, Vector},
+    Gas,
+    IntoStorageKey
+};
+
+/// Gas attached to the `migrate` call made once `upgrade` has deployed the new code
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+const GAS_FOR_MIGRATE: Gas = Gas(100_000_000_000_000);
+
+/// Key near-sdk stores the contract struct under
+const STATE_KEY: &[u8] = b"STATE";
+
+/// Key the layout version of the contract state is stored under
+const STATE_VERSION_KEY: &[u8] = b"STATE_VERSION";
+
+/// Layout version of the state written by this code, the number of its `VersionedContract` variant
+const STATE_VERSION: u8 = 1;
+
+/// Records that the contract state is stored in the current layout
+pub(crate) fn write_state_version() {
+    env::storage_write(STATE_VERSION_KEY, &STATE_VERSION.try_to_vec().unwrap());
+}
+
+/// Every layout a `Profile` has been stored with under `StorageKeys::Profiles`
+///
+/// Only layouts that have been deployed need a variant. When `Profile` changes after
+/// a deployment, copy its deployed layout to a frozen `ProfileV1` struct, point `V1`
+/// at it and add a `V2(Profile)` variant converted here
+#[derive(BorshDeserialize, BorshSerialize)]
+pub enum VersionedProfile {
+    V1(Profile)
+}
+
+impl From<VersionedProfile> for Profile {
+    fn from(profile: VersionedProfile) -> Self {
+        match profile {
+            VersionedProfile::V1(profile) => profile
+        }
+    }
+}
+
+/// A profile as first deployed, with collections under prefixes shared by every profile
+#[derive(BorshDeserialize)]
+pub struct ProfileV0 {
+    pub profile_type: ProfileType,
+    pub payment_interval: i32,
+    pub content_count: LookupMap<String, i32>,
+    pub content: LookupMap<String, String>,
+    pub subscribers: LookupMap<AccountId, i32>,
+    pub costs: LookupMap<String, U128>
+}
+
+impl ProfileV0 {
+    /// Keeps the legacy shared collections, so `migrate_profile` can still move them,
+    /// and gives the profile empty collections for everything added since
+    ///
+    /// Nothing is written, so this is safe to call from view methods
+    fn into_current(self, account_id: &AccountId) -> Profile {
+        let account_hash = account_hash(account_id);
+        Profile {
+            nonce: 0,
+            profile_type: self.profile_type,
+            payment_interval: self.payment_interval,
+            content_count: self.content_count,
+            content: self.content,
+            content_items: LookupMap::new(
+                StorageKeys::ProfileContentItems { account_hash: account_hash.clone() }
+            ),
+            content_ids: Vector::new(
+                StorageKeys::ProfileContentIds { account_hash: account_hash.clone() }
+            ),
+            subscribers: self.subscribers,
+            costs: self.costs,
+            bundles: UnorderedMap::new(
+                StorageKeys::ProfileBundles { account_hash: account_hash.clone() }
+            ),
+            access_mode: AccessMode::ContentCount,
+            expiries: LookupMap::new(
+                StorageKeys::ProfileExpiries { account_hash: account_hash.clone() }
+            ),
+            subscriber_count: 0,
+            max_post_gap: None,
+            last_published_block: None,
+            payments: LookupMap::new(
+                StorageKeys::ProfilePayments { account_hash: account_hash.clone() }
+            ),
+            payees: Vec::new(),
+            content_visibility: LookupMap::new(
+                StorageKeys::ProfileContentVisibility { account_hash: account_hash.clone() }
+            ),
+            purchases: LookupSet::new(
+                StorageKeys::ProfilePurchases { account_hash: account_hash.clone() }
+            ),
+            trial_posts: None,
+            trials: LookupMap::new(
+                StorageKeys::ProfileTrials { account_hash: account_hash.clone() }
+            ),
+            promo_codes: LookupMap::new(
+                StorageKeys::ProfilePromoCodes { account_hash }
+            )
+        }
+    }
+}
+
+/// The contract as first deployed
+///
+/// Old layouts are only read to recognise the stored state, so not every field is used
+#[allow(dead_code)]
+#[derive(BorshDeserialize)]
+pub struct ContractV0 {
+    pub data: LookupMap<AccountId, ProfileV0>
+}
+
+/// Every layout the contract state has been stored with, numbered by the version
+/// stored under `STATE_VERSION_KEY`. Only the first deployment has no stored version
+///
+/// The state is only read as this once, in `migrate`, so the variants aren't boxed
+#[allow(dead_code, clippy::large_enum_variant)]
+pub enum VersionedContract {
+    V0(ContractV0),
+    V1(Contract)
+}
+
+impl VersionedContract {
+    pub fn read() -> Self {
+        let state = match env::storage_read(STATE_KEY) {
+            Some(state) => state,
+            None => ContractError::InvalidState("there is no contract state to migrate".to_owned()).panic()
+        };
+        let version = match env::storage_read(STATE_VERSION_KEY) {
+            Some(version) => match u8::try_from_slice(&version) {
+                Ok(version) => version,
+                Err(_) => ContractError::InvalidState("could not read the contract state version".to_owned()).panic()
+            },
+            None => 0
+        };
+        let contract = match version {
+            0 => ContractV0::try_from_slice(&state).map(Self::V0),
+            STATE_VERSION => Contract::try_from_slice(&state).map(Self::V1),
+            _ => ContractError::InvalidState(format!("unknown contract state version {}", version)).panic()
+        };
+        match contract {
+            Ok(contract) => contract,
+            Err(_) => ContractError::InvalidState(format!("the contract state doesn't match version {}", version)).panic()
+        }
+    }
+
+    /// Profiles stored by V0 are left under `StorageKeys::Data` and read from there
+    /// by `internal_get_profile` until they are next saved. The first deployment had
+    /// no owner, so the contract is owned by its own account. Subscriptions made
+    /// before the upgrade can't be listed from `subscriptions`, LookupMaps can't be
+    /// iterated to fill it in
+    pub fn into_current(self) -> Contract {
+        match self {
+            Self::V0(_) => Contract::new(env::current_account_id()),
+            Self::V1(contract) => contract
+        }
+    }
+}
+
+/// Storage key of `account_id`s profile in the unversioned `StorageKeys::Data` map
+fn legacy_profile_key(account_id: &AccountId) -> Vec<u8> {
+    let mut key = StorageKeys::Data.into_storage_key();
+    key.extend(account_id.try_to_vec().unwrap());
+    key
+}
+
+impl Contract {
+    /// Reads a profile stored by the first deployment, before profiles were versioned
+    pub(crate) fn internal_get_legacy_profile(&self, account_id: &AccountId) -> Option<Profile> {
+        let profile = env::storage_read(&legacy_profile_key(account_id))?;
+        match ProfileV0::try_from_slice(&profile) {
+            Ok(profile) => Some(profile.into_current(account_id)),
+            Err(_) => ContractError::InvalidState("could not read legacy profile".to_owned()).panic()
+        }
+    }
+
+    pub(crate) fn internal_remove_legacy_profile(&mut self, account_id: &AccountId) {
+        env::storage_remove(&legacy_profile_key(account_id));
+    }
+
+    /// Deploys `code` to this account and calls `migrate` on it
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    pub(crate) fn internal_upgrade(&self, code: Vec<u8>) -> Promise {
+        self.assert_owner();
+        Promise::new(env::current_account_id())
+            .deploy_contract(code)
+            .function_call(
+                "migrate".to_owned(),
+                Vec::new(),
+                0,
+                GAS_FOR_MIGRATE
+            )
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Converts the state left by the first deployment, or an earlier upgrade, to the current layout
+    ///
+    /// Called by `upgrade` once the new code is deployed, or by the contract account
+    /// itself after deploying with its full access key
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let contract = VersionedContract::read().into_current();
+        write_state_version();
+        contract
+    }
+}
+
+/// Replaces the contract code with the wasm passed as the raw call input, then migrates the state
+///
+/// Only the owner can upgrade. The wasm is read as raw bytes rather than JSON
+/// so the largest contracts still fit in a single call
+#[cfg(target_arch = "wasm32")]
+#[no_mangle]
+pub extern "C" fn upgrade() {
+    env::setup_panic_hook();
+    let contract: Contract = match env::state_read() {
+        Some(contract) => contract,
+        None => ContractError::InvalidState("the contract is not initialized".to_owned()).panic()
+    };
+    let code = match env::input() {
+        Some(code) => code,
+        None => ContractError::InvalidInput("pass the new wasm as the call input".to_owned()).panic()
+    };
+    contract.internal_upgrade(code);
+}
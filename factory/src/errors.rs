@@ -0,0 +1,52 @@
+use near_sdk::{env, Balance};
+use std::fmt;
+
+/// Every way a factory call can fail
+///
+/// Raised as `"<CODE>: <message>"`, the same format the creator contracts use
+#[derive(Debug, PartialEq)]
+pub enum FactoryError {
+    NotOwner,
+    CodeNotSet,
+    AlreadyCreated,
+    CreatorNotFound,
+    InsufficientDeposit { required: Balance, attached: Balance },
+    InvalidInput(String)
+}
+
+impl FactoryError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotOwner => "NOT_OWNER",
+            Self::CodeNotSet => "CODE_NOT_SET",
+            Self::AlreadyCreated => "ALREADY_CREATED",
+            Self::CreatorNotFound => "CREATOR_NOT_FOUND",
+            Self::InsufficientDeposit { .. } => "INSUFFICIENT_DEPOSIT",
+            Self::InvalidInput(_) => "INVALID_INPUT"
+        }
+    }
+
+    /// Aborts the call with this error
+    pub fn panic(&self) -> ! {
+        env::panic_str(&self.to_string())
+    }
+}
+
+impl fmt::Display for FactoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: ", self.code())?;
+        match self {
+            Self::NotOwner => write!(f, "Only the factory owner can do this"),
+            Self::CodeNotSet => write!(f, "The creator contract code has not been uploaded yet"),
+            Self::AlreadyCreated => write!(f, "This account already has a creator contract"),
+            Self::CreatorNotFound => write!(f, "This account has no creator contract"),
+            Self::InsufficientDeposit { required, attached } => write!(
+                f,
+                "Attached deposit of {} is less than the required {}",
+                attached,
+                required
+            ),
+            Self::InvalidInput(message) => write!(f, "{}", message)
+        }
+    }
+}
@@ -0,0 +1,441 @@
+//! Deploys a single creator version of the subscription contract to a
+//! sub-account of the factory for each creator, and keeps a registry of them
+mod errors;
+use errors::FactoryError;
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    collections::UnorderedMap,
+    env,
+    json_types::U128,
+    serde::{Deserialize, Serialize},
+    serde_json::{json, Value},
+    AccountId,
+    Balance,
+    BorshStorageKey,
+    Gas,
+    PanicOnDefault,
+    Promise,
+    PromiseResult,
+    StorageUsage,
+    near_bindgen
+};
+
+/// Storage key the creator contract wasm is kept under, outside the factory struct
+const CODE_KEY: &[u8] = b"code";
+/// Bytes a new creator contract needs beyond its code, for its state and the creators first posts
+const CREATOR_CONTRACT_STATE_BYTES: StorageUsage = 50_000;
+/// Bytes a registry entry takes, the longest creator and contract ids plus trie overhead
+const REGISTRY_ENTRY_BYTES: StorageUsage = 250;
+const GAS_FOR_NEW_CREATOR_CONTRACT: Gas = Gas(20_000_000_000_000);
+const GAS_FOR_ON_CREATOR_CONTRACT_CREATED: Gas = Gas(10_000_000_000_000);
+/// Gas for a creator contract to deploy new code and migrate itself
+const GAS_FOR_UPGRADE: Gas = Gas(150_000_000_000_000);
+/// Gas for an owner call forwarded to a creator contract, enough for `withdraw_fees` and its callback
+const GAS_FOR_OWNER_CALL: Gas = Gas(50_000_000_000_000);
+/// Owner only creator contract methods the factory owner can call through `manage_creator_contract`
+///
+/// `upgrade` has its own method, and `set_owner` is left out so the factory keeps control of upgrades
+const CREATOR_CONTRACT_OWNER_METHODS: [&str; 9] = [
+    "pause",
+    "unpause",
+    "add_admin",
+    "remove_admin",
+    "set_platform_fee",
+    "withdraw_fees",
+    "withdraw_token_fees",
+    "add_accepted_token",
+    "remove_accepted_token"
+];
+
+#[derive(BorshStorageKey, BorshSerialize)]
+enum StorageKeys {
+    Creators
+}
+
+/// A registry entry returned by `get_creator_contracts`
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CreatorContract {
+    pub creator_id: AccountId,
+    pub contract_id: AccountId
+}
+
+#[near_bindgen]
+#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
+pub struct Factory {
+    /// uploads the creator contract code and rolls it out to creator contracts
+    owner_id: AccountId,
+    /// maps each creator to the account their contract is deployed to
+    creators: UnorderedMap<AccountId, AccountId>
+}
+
+impl Factory {
+    fn assert_owner(&self) {
+        if env::predecessor_account_id() != self.owner_id {
+            FactoryError::NotOwner.panic();
+        }
+    }
+
+    fn creator_contract_code() -> Vec<u8> {
+        match env::storage_read(CODE_KEY) {
+            Some(code) => code,
+            None => FactoryError::CodeNotSet.panic()
+        }
+    }
+
+    /// Replaces the wasm deployed to new creator contracts
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    pub(crate) fn internal_set_creator_contract_code(&mut self, code: Vec<u8>) {
+        self.assert_owner();
+        env::storage_write(CODE_KEY, &code);
+    }
+}
+
+#[near_bindgen]
+impl Factory {
+    #[init]
+    pub fn new(owner_id: AccountId) -> Self {
+        Self {
+            owner_id,
+            creators: UnorderedMap::new(StorageKeys::Creators)
+        }
+    }
+
+    pub fn get_owner(&self) -> AccountId {
+        self.owner_id.clone()
+    }
+
+    /// yoctoNEAR to attach to `create_creator_contract`
+    ///
+    /// Covers storage for the contract code, its state and the factory's registry entry
+    pub fn get_creation_cost(&self) -> U128 {
+        let code_bytes = match env::storage_read(CODE_KEY) {
+            Some(code) => code.len() as StorageUsage,
+            None => FactoryError::CodeNotSet.panic()
+        };
+        U128::from(
+            Balance::from(code_bytes + CREATOR_CONTRACT_STATE_BYTES + REGISTRY_ENTRY_BYTES)
+                * env::storage_byte_cost()
+        )
+    }
+
+    /// Deploys a contract hosting only the callers creator profile to `<name>.<factory>`
+    ///
+    /// `cost` (whole NEAR) and `payment_interval` set up the profile as in `add_profile`.
+    /// The attached deposit, less the registry entry, funds the new account. The
+    /// caller is registered straight away, so a second call fails while this one is
+    /// pending, and is unregistered and refunded if the deployment fails
+    #[payable]
+    pub fn create_creator_contract(&mut self, name: String, cost: String, payment_interval: String) -> Promise {
+        let creator_id = env::predecessor_account_id();
+        if self.creators.get(&creator_id).is_some() {
+            FactoryError::AlreadyCreated.panic();
+        }
+        let contract_id: AccountId = match format!("{}.{}", name, env::current_account_id()).parse() {
+            Ok(contract_id) => contract_id,
+            Err(_) => FactoryError::InvalidInput(format!("{} is not a valid account name", name)).panic()
+        };
+        let required = self.get_creation_cost().0;
+        let deposit = env::attached_deposit();
+        if deposit < required {
+            FactoryError::InsufficientDeposit {
+                required,
+                attached: deposit
+            }.panic();
+        }
+        let registry_cost = Balance::from(REGISTRY_ENTRY_BYTES) * env::storage_byte_cost();
+        self.creators.insert(&creator_id, &contract_id);
+        Promise::new(contract_id.clone())
+            .create_account()
+            .transfer(deposit - registry_cost)
+            .deploy_contract(Self::creator_contract_code())
+            .function_call(
+                "new_creator_contract".to_owned(),
+                json!({
+                    "owner_id": env::current_account_id(),
+                    "creator_id": creator_id,
+                    "cost": cost,
+                    "payment_interval": payment_interval
+                }).to_string().into_bytes(),
+                0,
+                GAS_FOR_NEW_CREATOR_CONTRACT
+            )
+            .then(
+                Promise::new(env::current_account_id()).function_call(
+                    "on_creator_contract_created".to_owned(),
+                    json!({
+                        "creator_id": creator_id,
+                        "contract_id": contract_id,
+                        "deposit": U128::from(deposit)
+                    }).to_string().into_bytes(),
+                    0,
+                    GAS_FOR_ON_CREATOR_CONTRACT_CREATED
+                )
+            )
+    }
+
+    /// Keeps the registry entry of the new contract, or removes it and refunds the
+    /// creator if any step of its deployment failed
+    #[private]
+    pub fn on_creator_contract_created(&mut self, creator_id: AccountId, contract_id: AccountId, deposit: U128) -> bool {
+        match env::promise_result(0) {
+            PromiseResult::Successful(_) => true,
+            _ => {
+                if self.creators.get(&creator_id) == Some(contract_id) {
+                    self.creators.remove(&creator_id);
+                }
+                Promise::new(creator_id).transfer(deposit.0);
+                false
+            }
+        }
+    }
+
+    /// Deploys the current creator contract code to the contract of `creator_id`
+    pub fn upgrade_creator_contract(&mut self, creator_id: AccountId) -> Promise {
+        self.assert_owner();
+        let contract_id = match self.creators.get(&creator_id) {
+            Some(contract_id) => contract_id,
+            None => FactoryError::CreatorNotFound.panic()
+        };
+        Promise::new(contract_id).function_call(
+            "upgrade".to_owned(),
+            Self::creator_contract_code(),
+            0,
+            GAS_FOR_UPGRADE
+        )
+    }
+
+    /// Calls the owner only `method_name` on the contract of `creator_id` with the JSON `args`
+    ///
+    /// The factory owns every creator contract, so its owner manages them through here,
+    /// e.g. `{"method_name": "set_platform_fee", "args": {"fee_bps": 500}}`. The attached
+    /// deposit is forwarded, `withdraw_fees` and `withdraw_token_fees` need one yoctoNEAR and a `receiver_id`
+    #[payable]
+    pub fn manage_creator_contract(&mut self, creator_id: AccountId, method_name: String, args: Value) -> Promise {
+        self.assert_owner();
+        if !CREATOR_CONTRACT_OWNER_METHODS.contains(&method_name.as_str()) {
+            FactoryError::InvalidInput(format!("{} can't be called through the factory", method_name)).panic();
+        }
+        let contract_id = match self.creators.get(&creator_id) {
+            Some(contract_id) => contract_id,
+            None => FactoryError::CreatorNotFound.panic()
+        };
+        Promise::new(contract_id).function_call(
+            method_name,
+            args.to_string().into_bytes(),
+            env::attached_deposit(),
+            GAS_FOR_OWNER_CALL
+        )
+    }
+
+    pub fn get_creator_contract(&self, creator_id: AccountId) -> Option<AccountId> {
+        self.creators.get(&creator_id)
+    }
+
+    /// Lists registered creators and their contracts, starting at `from_index` (default 0)
+    /// and returning at most `limit` (default 10)
+    pub fn get_creator_contracts(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<CreatorContract> {
+        self.creators
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(10) as usize)
+            .map(|(creator_id, contract_id)| CreatorContract {
+                creator_id,
+                contract_id
+            })
+            .collect()
+    }
+}
+
+/// Stores the wasm passed as the raw call input as the code for new creator contracts
+///
+/// Only the owner can set it. Read as raw bytes, like the creator contracts `upgrade`
+#[cfg(target_arch = "wasm32")]
+#[no_mangle]
+pub extern "C" fn set_creator_contract_code() {
+    env::setup_panic_hook();
+    let mut factory: Factory = match env::state_read() {
+        Some(factory) => factory,
+        None => env::panic_str("the factory is not initialized")
+    };
+    let code = match env::input() {
+        Some(code) => code,
+        None => FactoryError::InvalidInput("pass the wasm as the call input".to_owned()).panic()
+    };
+    factory.internal_set_creator_contract_code(code);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{get_created_receipts, VMContextBuilder};
+    use near_sdk::{testing_env, RuntimeFeesConfig, VMConfig, VMContext};
+
+    fn get_context(predecessor_address: &str, attached_deposit: u128) -> VMContext {
+        VMContextBuilder::new()
+            .current_account_id("factory.near".parse().unwrap())
+            .predecessor_account_id(predecessor_address.parse().unwrap())
+            .attached_deposit(attached_deposit)
+            .account_balance(10u128.pow(26))
+            .build()
+    }
+
+    /// asserts `result` is a panic raised with the code of `error`
+    fn assert_factory_error<T>(result: std::thread::Result<T>, error: FactoryError) {
+        let message = match result {
+            Ok(_) => panic!("expected {} but the call succeeded", error.code()),
+            Err(payload) => match payload.downcast::<String>() {
+                Ok(message) => *message,
+                Err(_) => panic!("expected {} but the panic had no message", error.code())
+            }
+        };
+        assert!(
+            message.contains(error.code()),
+            "expected {} but got {}",
+            error.code(),
+            message
+        );
+    }
+
+    /// returns a factory owned by `owner.near` with 1000 bytes of creator contract code
+    fn factory_with_code() -> Factory {
+        testing_env!(get_context("owner.near", 0));
+        let mut factory = Factory::new("owner.near".parse().unwrap());
+        factory.internal_set_creator_contract_code(vec![0; 1000]);
+        factory
+    }
+
+    #[test]
+    fn test_create_creator_contract() {
+        let mut factory = factory_with_code();
+        let cost = factory.get_creation_cost();
+        assert_eq!(
+            U128::from(Balance::from(1000 + CREATOR_CONTRACT_STATE_BYTES + REGISTRY_ENTRY_BYTES) * 10u128.pow(19)),
+            cost
+        );
+
+        testing_env!(get_context("creator.near", cost.0));
+        factory.create_creator_contract("creator".to_owned(), "1".to_owned(), "3".to_owned());
+        let receipts = get_created_receipts();
+        assert_eq!(2, receipts.len());
+        assert_eq!("creator.factory.near", receipts[0].receiver_id.as_str());
+        assert_eq!(4, receipts[0].actions.len());
+        assert_eq!("factory.near", receipts[1].receiver_id.as_str());
+        assert_eq!(
+            Some("creator.factory.near".parse().unwrap()),
+            factory.get_creator_contract("creator.near".parse().unwrap())
+        );
+
+        // the pending entry stops a second contract being created before the first one's callback runs
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            factory.create_creator_contract("creator2".to_owned(), "1".to_owned(), "3".to_owned());
+        }));
+        assert_factory_error(result, FactoryError::AlreadyCreated);
+
+        testing_env!(get_context("other.near", cost.0));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            factory.create_creator_contract("other.".to_owned(), "1".to_owned(), "3".to_owned());
+        }));
+        assert_factory_error(result, FactoryError::InvalidInput(String::new()));
+
+        testing_env!(get_context("other.near", cost.0 - 1));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            factory.create_creator_contract("other".to_owned(), "1".to_owned(), "3".to_owned());
+        }));
+        assert_factory_error(result, FactoryError::InsufficientDeposit { required: 0, attached: 0 });
+    }
+
+    #[test]
+    fn test_set_creator_contract_code_owner_only() {
+        testing_env!(get_context("owner.near", 0));
+        let mut factory = Factory::new("owner.near".parse().unwrap());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            factory.get_creation_cost();
+        }));
+        assert_factory_error(result, FactoryError::CodeNotSet);
+
+        testing_env!(get_context("creator.near", 0));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            factory.internal_set_creator_contract_code(vec![0; 10]);
+        }));
+        assert_factory_error(result, FactoryError::NotOwner);
+    }
+
+    #[test]
+    fn test_manage_creator_contract() {
+        let mut factory = factory_with_code();
+        factory.creators.insert(&"creator.near".parse().unwrap(), &"creator.factory.near".parse().unwrap());
+
+        testing_env!(get_context("owner.near", 1));
+        factory.manage_creator_contract(
+            "creator.near".parse().unwrap(),
+            "withdraw_fees".to_owned(),
+            json!({ "amount": "100", "receiver_id": "owner.near" })
+        );
+        let receipts = get_created_receipts();
+        assert_eq!(1, receipts.len());
+        assert_eq!("creator.factory.near", receipts[0].receiver_id.as_str());
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            factory.manage_creator_contract("creator.near".parse().unwrap(), "set_owner".to_owned(), json!({}));
+        }));
+        assert_factory_error(result, FactoryError::InvalidInput(String::new()));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            factory.manage_creator_contract("other.near".parse().unwrap(), "pause".to_owned(), json!({}));
+        }));
+        assert_factory_error(result, FactoryError::CreatorNotFound);
+
+        testing_env!(get_context("creator.near", 0));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            factory.manage_creator_contract("creator.near".parse().unwrap(), "pause".to_owned(), json!({}));
+        }));
+        assert_factory_error(result, FactoryError::NotOwner);
+    }
+
+    #[test]
+    fn test_on_creator_contract_created() {
+        let mut factory = factory_with_code();
+        factory.creators.insert(&"creator.near".parse().unwrap(), &"creator.factory.near".parse().unwrap());
+        testing_env!(
+            get_context("factory.near", 0),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])]
+        );
+        assert!(factory.on_creator_contract_created(
+            "creator.near".parse().unwrap(),
+            "creator.factory.near".parse().unwrap(),
+            U128::from(10u128.pow(24))
+        ));
+        assert_eq!(
+            Some("creator.factory.near".parse().unwrap()),
+            factory.get_creator_contract("creator.near".parse().unwrap())
+        );
+        assert_eq!(
+            vec![CreatorContract {
+                creator_id: "creator.near".parse().unwrap(),
+                contract_id: "creator.factory.near".parse().unwrap()
+            }],
+            factory.get_creator_contracts(None, None)
+        );
+
+        factory.creators.insert(&"other.near".parse().unwrap(), &"other.factory.near".parse().unwrap());
+        testing_env!(
+            get_context("factory.near", 0),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed]
+        );
+        assert!(!factory.on_creator_contract_created(
+            "other.near".parse().unwrap(),
+            "other.factory.near".parse().unwrap(),
+            U128::from(10u128.pow(24))
+        ));
+        assert_eq!(None, factory.get_creator_contract("other.near".parse().unwrap()));
+        let receipts = get_created_receipts();
+        assert_eq!(1, receipts.len());
+        assert_eq!("other.near", receipts[0].receiver_id.as_str());
+    }
+}
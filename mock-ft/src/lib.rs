@@ -0,0 +1,33 @@
+//! Minimal NEP-141 token used by the integration tests to pay for subscriptions
+use near_contract_standards::fungible_token::FungibleToken;
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    json_types::U128,
+    AccountId,
+    PanicOnDefault,
+    PromiseOrValue,
+    near_bindgen
+};
+
+#[near_bindgen]
+#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
+pub struct Contract {
+    token: FungibleToken,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Mints `total_supply` to `owner_id`
+    #[init]
+    pub fn new(owner_id: AccountId, total_supply: U128) -> Self {
+        let mut token = FungibleToken::new(b"t".to_vec());
+        token.internal_register_account(&owner_id);
+        token.internal_deposit(&owner_id, total_supply.0);
+        Self {
+            token
+        }
+    }
+}
+
+near_contract_standards::impl_fungible_token_core!(Contract, token);
+near_contract_standards::impl_fungible_token_storage!(Contract, token);
@@ -2,6 +2,9 @@ use workspaces::{Account, Worker, Contract, DevNetwork};
 
 pub mod contract_call_tester {
     use super::*;
+    /// 300 TGas, the most a single call can attach
+    const MAX_GAS: u64 = 300_000_000_000_000;
+
     /// Calls a contract method on a contract
     /// 
     /// Receives an expected value
@@ -50,4 +53,66 @@ pub mod contract_call_tester {
         }
         Ok(())
     }
+
+    /// Calls a payable contract method on a contract, attaching `deposit` yoctoNEAR
+    /// and enough gas for cross contract calls
+    ///
+    /// Only checks that the call was executed, returned values are not compared
+    pub async fn test_payable_contract_call(
+        caller: &Account,
+        worker: &Worker<impl DevNetwork>,
+        contract: &Contract,
+        method: &str,
+        arguments: serde_json::Value,
+        deposit: u128
+    ) -> anyhow::Result<()> {
+        caller
+            .call(
+                &worker,
+                contract.id().to_owned(),
+                method
+            )
+            .args_json(arguments)?
+            .deposit(deposit)
+            .gas(MAX_GAS)
+            .transact()
+            .await?;
+        Ok(())
+    }
+
+    /// Calls a contract method that returns a JSON object
+    ///
+    /// Only the fields present in `expected_result` are compared, so values that
+    /// can't be known ahead of the call (e.g. timestamps) can be left out
+    pub async fn test_contract_call_json(
+        caller: &Account,
+        worker: &Worker<impl DevNetwork>,
+        contract: &Contract,
+        method: &str,
+        arguments: serde_json::Value,
+        expected_result: serde_json::Value
+    ) -> anyhow::Result<()> {
+        let result = caller
+            .call(
+                &worker,
+                contract.id().to_owned(),
+                method
+            )
+            .args_json(arguments)?
+            .transact()
+            .await?
+            .json::<serde_json::Value>()?;
+
+        let expected_fields = match expected_result.as_object() {
+            Some(fields) => fields,
+            None => return Err(anyhow::anyhow!("expected_result must be a JSON object"))
+        };
+        if expected_fields.iter().all(|(key, value)| result.get(key) == Some(value)) {
+            println!("{} test: passed", method);
+        } else {
+            println!("{} test: failed", method);
+            println!("left: {} != right: {}", result, expected_result);
+        }
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -2,6 +2,7 @@ mod utils;
 use utils::utils::*;
 mod contract_call_tester;
 use contract_call_tester::contract_call_tester::*;
+use near_units::parse_near;
 use serde_json::json;
 use workspaces::prelude::*;
 
@@ -22,6 +23,12 @@ async fn main() -> anyhow::Result<()> {
     let contract = worker_deployer.dev_deploy(wasm)
         .await
         .expect("failed to deploy contract");
+    contract.call(&worker_deployer, "new")
+        .args_json(json!({
+            "owner_id": contract.id()
+        }))?
+        .transact()
+        .await?;
 
     // create an Account struct for the creator profile type
     let creator = worker_creator.dev_create_account()
@@ -48,6 +55,24 @@ async fn main() -> anyhow::Result<()> {
         .await
         .expect("failed to create consumer_low_balace account");
 
+    // register every account for storage before they write to the contract
+    for (account, worker) in [
+        (&creator, &worker_creator),
+        (&consumer, &worker_consumer),
+        (&consumer_low_balace, &worker_consumer_low_balance)
+    ] {
+        match test_payable_contract_call(account, worker, &contract, "storage_deposit",
+            json!({}),
+            parse_near!("1 N")
+        ).await {
+            Ok(_) => println!("storage_deposit with {}: passed", account.id()),
+            Err(error) => {
+                println!("storage_deposit with {}: failed", account.id());
+                println!("error: {}", error);
+            }
+        };
+    }
+
     // tests add_profile method of contract
     match test_contract_call(&creator, &worker_creator,&contract,"add_profile",
         json!({
@@ -66,11 +91,17 @@ async fn main() -> anyhow::Result<()> {
     };
 
     // test get_profile method
-    match test_contract_call(&creator, &worker_creator, &contract, "get_profile",
+    match test_contract_call_json(&creator, &worker_creator, &contract, "get_profile",
         json!({
             "account_id": &creator.id().to_owned()
         }),
-        "", false
+        json!({
+            "profile_type": "creator",
+            "cost": "1000000000000000000000000",
+            "payment_interval": 3,
+            "content_count": 0,
+            "subscriber_count": 0
+        })
     ).await {
         Ok(_) => println!("get_profile with creator: passed"),
         Err(error) => {
@@ -83,7 +114,11 @@ async fn main() -> anyhow::Result<()> {
     match test_contract_call(&creator, &worker_creator, &contract, "add_content",
         json!({
             "date": &content_id,
-            "content": &content
+            "content": {
+                "title": "first video",
+                "body": &content,
+                "mime_type": "text/uri-list"
+            }
         }),
         "", false
     ).await {
@@ -92,21 +127,21 @@ async fn main() -> anyhow::Result<()> {
     };
     
     // tests get_content method of contract - called by profile creator
-    test_contract_call(&creator, &worker_creator, &contract, "get_content",
+    test_contract_call_json(&creator, &worker_creator, &contract, "get_content",
         json!({
             "creator_address": creator.id(),
             "date": &content_id
         }),
-        &content, true
+        json!({ "body": &content })
     ).await.expect("error when getting content");
     
     // tests get_content method of contract - expects a panic as called by non-subscriber
-    match test_contract_call(&consumer, &worker_consumer, &contract, "get_content",
+    match test_contract_call_json(&consumer, &worker_consumer, &contract, "get_content",
         json!({
             "creator_address": &creator.id().to_owned(),
             "date": &content_id
         }),
-        &content, true
+        json!({ "body": &content })
     ).await {
         Ok(_) => println!("get_content with none subscriber: failed"),
         Err(error) => {
@@ -117,11 +152,11 @@ async fn main() -> anyhow::Result<()> {
     
     // subscribe then get_content
     // subscribe
-    match test_contract_call(&consumer, &worker_consumer, &contract, "subscribe",
+    match test_payable_contract_call(&consumer, &worker_consumer, &contract, "subscribe",
         json!({
             "creator_address": &creator.id().to_owned()
         }),
-        "", false
+        parse_near!("1 N")
     ).await {
         Ok(_) => println!("subscribe with consumer: passed"),
         Err(error) => {
@@ -131,13 +166,47 @@ async fn main() -> anyhow::Result<()> {
     };
 
     // get_content now that consumer has subscribed to creator - expects content to be returned
-    test_contract_call(&consumer, &worker_consumer, &contract, "get_content",
+    test_contract_call_json(&consumer, &worker_consumer, &contract, "get_content",
         json!({
             "creator_address": &creator.id().to_owned(),
             "date": &content_id
         }),
-        &content, true
+        json!({ "body": &content })
     ).await.expect("error when getting content with subscriber");
+
+    // the subscription was minted to the consumer as a pass
+    test_contract_call(&consumer, &worker_consumer, &contract, "nft_supply_for_owner",
+        json!({
+            "account_id": &consumer.id().to_owned()
+        }),
+        "1", true
+    ).await.expect("error when getting subscription passes");
+
+    // the subscription payment is held for the creator until they withdraw it
+    test_contract_call(&creator, &worker_creator, &contract, "withdrawable_balance",
+        json!({
+            "creator_address": &creator.id().to_owned()
+        }),
+        "1000000000000000000000000", true
+    ).await.expect("error when getting withdrawable balance");
+    match test_payable_contract_call(&creator, &worker_creator, &contract, "withdraw",
+        json!({
+            "amount": "1000000000000000000000000"
+        }),
+        1
+    ).await {
+        Ok(_) => println!("withdraw with creator: passed"),
+        Err(error) => {
+            println!("withdraw with creator: failed");
+            println!("error: {}", error);
+        }
+    };
+    test_contract_call(&creator, &worker_creator, &contract, "withdrawable_balance",
+        json!({
+            "creator_address": &creator.id().to_owned()
+        }),
+        "0", true
+    ).await.expect("error when getting withdrawable balance after withdraw");
     
     // subscribe with insufficient funds
     // update cost to above test account balance
@@ -159,8 +228,8 @@ async fn main() -> anyhow::Result<()> {
         json!({}), "201", true
     ).await.expect("error when getting getting cost");
 
-    match test_contract_call(&consumer_low_balace, &worker_consumer_low_balance, &contract, "subscribe",
-        json!({"creator_address": &creator.id().to_owned()}), "", true
+    match test_payable_contract_call(&consumer_low_balace, &worker_consumer_low_balance, &contract, "subscribe",
+        json!({"creator_address": &creator.id().to_owned()}), parse_near!("201 N")
     ).await {
         Ok(_) => println!("subscribe with consumer with low balance: failed"),
         Err(_) => println!("subscribe with consumer with low balance: passed")
@@ -181,11 +250,11 @@ async fn main() -> anyhow::Result<()> {
     };
 
     // subscribe at new lower cost
-    match test_contract_call(&consumer_low_balace, &worker_consumer_low_balance, &contract, "subscribe",
+    match test_payable_contract_call(&consumer_low_balace, &worker_consumer_low_balance, &contract, "subscribe",
         json!({
             "creator_address": &creator.id().to_owned()
         }),
-        "", false
+        parse_near!("2 N")
     ).await {
         Ok(_) => println!("subscribe with consumer now that cost lowered: passed"),
         Err(error) => {
@@ -199,7 +268,11 @@ async fn main() -> anyhow::Result<()> {
         match test_contract_call(&creator, &worker_creator, &contract, "add_content",
             json!({
                 "date": content.0,
-                "content": content.1
+                "content": {
+                    "title": content.0,
+                    "body": content.1,
+                    "mime_type": "text/plain"
+                }
             }),
             "", false
         ).await {
@@ -209,12 +282,12 @@ async fn main() -> anyhow::Result<()> {
     }
 
     // get content that consumer should not have access too: expects a panicjjj
-    match test_contract_call(&consumer, &worker_consumer, &contract, "get_content",
+    match test_contract_call_json(&consumer, &worker_consumer, &contract, "get_content",
         json!({
             "creator_address": &creator.id().to_owned(),
             "date": "5"
         }),
-        "content 5", true
+        json!({ "body": "content 5" })
     ).await {
         Ok(_) => println!("get_content when no access: failed"),
         Err(error) => println!("get_content when no access: passed {}", error)
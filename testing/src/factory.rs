@@ -0,0 +1,135 @@
+mod utils;
+use utils::utils::*;
+mod contract_call_tester;
+use contract_call_tester::contract_call_tester::*;
+use serde_json::json;
+use workspaces::prelude::*;
+
+const DECENTRALISED_CONTENT_SUBSCRIPTION_NEAR_WASM_FILEPATH: &str = "contracts/res/decentralised_content_subscription_near.wasm";
+const FACTORY_WASM_FILEPATH: &str = "factory/res/content_subscription_factory.wasm";
+/// 300 TGas, enough to create, deploy and initialise a creator contract
+const MAX_GAS: u64 = 300_000_000_000_000;
+
+/// Deploys a creator contract through the factory and checks it is registered
+/// and hosts the creators profile
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    // build the contracts if not yet built
+    let wasm = get_wasm(DECENTRALISED_CONTENT_SUBSCRIPTION_NEAR_WASM_FILEPATH);
+    let factory_wasm = get_wasm(FACTORY_WASM_FILEPATH);
+
+    let worker = workspaces::testnet();
+    let factory = worker.dev_deploy(factory_wasm)
+        .await
+        .expect("failed to deploy factory");
+    factory.call(&worker, "new")
+        .args_json(json!({
+            "owner_id": factory.id()
+        }))?
+        .transact()
+        .await?;
+    // upload the creator contract code as raw bytes
+    factory.call(&worker, "set_creator_contract_code")
+        .args(wasm)
+        .gas(MAX_GAS)
+        .transact()
+        .await?;
+
+    let creator = worker.dev_create_account()
+        .await
+        .expect("failed to create creator account");
+    let creation_cost: String = factory.view(&worker, "get_creation_cost", Vec::new())
+        .await?
+        .json()?;
+    match test_payable_contract_call(&creator, &worker, &factory, "create_creator_contract",
+        json!({
+            "name": "creator",
+            "cost": "1",
+            "payment_interval": "3"
+        }),
+        creation_cost.parse::<u128>()?
+    ).await {
+        Ok(_) => println!("create_creator_contract: passed"),
+        Err(error) => {
+            println!("create_creator_contract: failed");
+            println!("error: {}", error);
+        }
+    };
+
+    let contract_id = format!("creator.{}", factory.id());
+    test_contract_call(&creator, &worker, &factory, "get_creator_contract",
+        json!({
+            "creator_id": creator.id()
+        }),
+        &contract_id, true
+    ).await.expect("error when getting creator contract");
+
+    // the new contract only hosts the creators profile and is owned by the factory
+    let result = creator
+        .call(&worker, contract_id.parse()?, "get_profile")
+        .args_json(json!({
+            "account_id": creator.id()
+        }))?
+        .transact()
+        .await?
+        .json::<serde_json::Value>()?;
+    if result["payment_interval"] == json!(3) && result["profile_type"] == json!("creator") {
+        println!("get_profile on creator contract: passed");
+    } else {
+        println!("get_profile on creator contract: failed");
+        println!("result: {}", result);
+    }
+    let owner = creator
+        .call(&worker, contract_id.parse()?, "get_owner")
+        .transact()
+        .await?
+        .json::<String>()?;
+    if owner == factory.id().as_str() {
+        println!("get_owner on creator contract: passed");
+    } else {
+        println!("get_owner on creator contract: failed");
+        println!("left: {} != right: {}", owner, factory.id());
+    }
+
+    // the factory owner manages the creator contract through the factory
+    factory.call(&worker, "manage_creator_contract")
+        .args_json(json!({
+            "creator_id": creator.id(),
+            "method_name": "set_platform_fee",
+            "args": { "fee_bps": 500 }
+        }))?
+        .gas(MAX_GAS)
+        .transact()
+        .await?;
+    let platform_fee = creator
+        .call(&worker, contract_id.parse()?, "get_platform_fee")
+        .transact()
+        .await?
+        .json::<serde_json::Value>()?;
+    if platform_fee["fee_bps"] == json!(500) {
+        println!("manage_creator_contract set_platform_fee: passed");
+    } else {
+        println!("manage_creator_contract set_platform_fee: failed");
+        println!("result: {}", platform_fee);
+    }
+
+    // a creator can only have one contract
+    match creator
+        .call(&worker, factory.id().to_owned(), "create_creator_contract")
+        .args_json(json!({
+            "name": "creator2",
+            "cost": "1",
+            "payment_interval": "3"
+        }))?
+        .deposit(creation_cost.parse::<u128>()?)
+        .gas(MAX_GAS)
+        .transact()
+        .await {
+        Ok(_) => println!("create_creator_contract twice: failed"),
+        Err(_) => println!("create_creator_contract twice: passed")
+    };
+
+    remove_near_credentials();
+
+    Ok(())
+}
@@ -0,0 +1,167 @@
+mod utils;
+use utils::utils::*;
+mod contract_call_tester;
+use contract_call_tester::contract_call_tester::*;
+use near_units::parse_near;
+use serde_json::json;
+use workspaces::prelude::*;
+
+const DECENTRALISED_CONTENT_SUBSCRIPTION_NEAR_WASM_FILEPATH: &str = "contracts/res/decentralised_content_subscription_near.wasm";
+const MOCK_FT_WASM_FILEPATH: &str = "mock-ft/res/mock_ft.wasm";
+
+/// Subscribes to a creator by paying in a NEP-141 token with `ft_transfer_call`
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    // build the contracts if not yet built
+    let wasm = get_wasm(DECENTRALISED_CONTENT_SUBSCRIPTION_NEAR_WASM_FILEPATH);
+    let ft_wasm = get_wasm(MOCK_FT_WASM_FILEPATH);
+
+    let worker = workspaces::testnet();
+
+    // deploy the subscription contract and the token
+    let contract = worker.dev_deploy(wasm)
+        .await
+        .expect("failed to deploy contract");
+    let token = worker.dev_deploy(ft_wasm)
+        .await
+        .expect("failed to deploy token");
+    contract.call(&worker, "new")
+        .args_json(json!({
+            "owner_id": contract.id()
+        }))?
+        .transact()
+        .await?;
+    token.call(&worker, "new")
+        .args_json(json!({
+            "owner_id": token.id(),
+            "total_supply": "1000000"
+        }))?
+        .transact()
+        .await?;
+    contract.call(&worker, "add_accepted_token")
+        .args_json(json!({
+            "token_id": token.id()
+        }))?
+        .transact()
+        .await?;
+
+    let creator = worker.dev_create_account()
+        .await
+        .expect("failed to create creator account");
+    let consumer = worker.dev_create_account()
+        .await
+        .expect("failed to create consumer account");
+    // cost to subscribe to creators profile in the tokens smallest unit
+    let creator_token_cost = "100";
+    let content = "https://www.youtube.com/watch?v=MddGbXgIt2E".to_owned();
+    let content_id = "1".to_owned();
+
+    // register the creator, consumer and contract with the token, and the creator and consumer with the contract
+    for account_id in [creator.id(), consumer.id(), contract.id()] {
+        test_payable_contract_call(&consumer, &worker, &token, "storage_deposit",
+            json!({
+                "account_id": account_id
+            }),
+            parse_near!("0.01 N")
+        ).await.expect("error when registering with token");
+    }
+    for account in [&creator, &consumer] {
+        test_payable_contract_call(account, &worker, &contract, "storage_deposit",
+            json!({}),
+            parse_near!("1 N")
+        ).await.expect("error when registering with contract");
+    }
+
+    // give the consumer some tokens to pay with
+    token.call(&worker, "ft_transfer")
+        .args_json(json!({
+            "receiver_id": consumer.id(),
+            "amount": "1000"
+        }))?
+        .deposit(1)
+        .transact()
+        .await?;
+
+    // set up a creator profile priced in the token
+    test_contract_call(&creator, &worker, &contract, "add_profile",
+        json!({
+            "account_id": creator.id(),
+            "profile_type": "creator",
+            "cost": "1",
+            "payment_interval": "3"
+        }),
+        "", false
+    ).await.expect("error when adding profile");
+    test_contract_call(&creator, &worker, &contract, "set_token_cost",
+        json!({
+            "token_id": token.id(),
+            "cost": creator_token_cost
+        }),
+        "", false
+    ).await.expect("error when setting token cost");
+    test_contract_call(&creator, &worker, &contract, "add_content",
+        json!({
+            "date": &content_id,
+            "content": {
+                "title": "first video",
+                "body": &content,
+                "mime_type": "text/uri-list"
+            }
+        }),
+        "", false
+    ).await.expect("error when adding content");
+
+    // pay more than the cost, the unused amount should be refunded by the token
+    match test_payable_contract_call(&consumer, &worker, &token, "ft_transfer_call",
+        json!({
+            "receiver_id": contract.id(),
+            "amount": "150",
+            "msg": json!({ "creator_address": creator.id() }).to_string()
+        }),
+        1
+    ).await {
+        Ok(_) => println!("ft_transfer_call with consumer: passed"),
+        Err(error) => {
+            println!("ft_transfer_call with consumer: failed");
+            println!("error: {}", error);
+        }
+    };
+
+    // the payment is held by the contract until the creator withdraws it
+    test_contract_call(&creator, &worker, &contract, "withdrawable_token_balance",
+        json!({
+            "account_id": creator.id(),
+            "token_id": token.id()
+        }),
+        creator_token_cost, true
+    ).await.expect("error when getting withdrawable token balance");
+    test_payable_contract_call(&creator, &worker, &contract, "withdraw_token",
+        json!({
+            "token_id": token.id(),
+            "amount": creator_token_cost
+        }),
+        1
+    ).await.expect("error when withdrawing token payments");
+
+    for (account_id, expected_balance) in [(consumer.id(), "900"), (creator.id(), "100")] {
+        test_contract_call(&consumer, &worker, &token, "ft_balance_of",
+            json!({
+                "account_id": account_id
+            }),
+            expected_balance, true
+        ).await.expect("error when getting token balance");
+    }
+
+    // the consumer now has access to the creators content
+    test_contract_call_json(&consumer, &worker, &contract, "get_content",
+        json!({
+            "creator_address": creator.id(),
+            "date": &content_id
+        }),
+        json!({ "body": &content })
+    ).await.expect("error when getting content with token subscriber");
+
+    remove_near_credentials();
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+mod utils;
+use utils::utils::*;
+mod contract_call_tester;
+use contract_call_tester::contract_call_tester::*;
+use serde_json::json;
+use workspaces::prelude::*;
+
+const DECENTRALISED_CONTENT_SUBSCRIPTION_NEAR_WASM_FILEPATH: &str = "contracts/res/decentralised_content_subscription_near.wasm";
+/// The contract as first deployed, kept fixed so every run upgrades from the same code
+const OLD_DECENTRALISED_CONTENT_SUBSCRIPTION_NEAR_WASM_FILEPATH: &str = "testing/res/decentralised_content_subscription_near_old.wasm";
+/// 300 TGas, enough to deploy the new code and run `migrate`
+const MAX_GAS: u64 = 300_000_000_000_000;
+
+/// Deploys the originally deployed wasm kept in `testing/res`, upgrades it to a fresh build
+/// and checks the profile and content written before the upgrade survived
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let old_wasm = std::fs::read(OLD_DECENTRALISED_CONTENT_SUBSCRIPTION_NEAR_WASM_FILEPATH)
+        .expect("testing/res has no wasm to upgrade from");
+
+    let worker = workspaces::testnet();
+    let contract = worker.dev_deploy(old_wasm)
+        .await
+        .expect("failed to deploy contract");
+    let creator = worker.dev_create_account()
+        .await
+        .expect("failed to create creator account");
+    let content = "https://www.youtube.com/watch?v=MddGbXgIt2E".to_owned();
+    let content_id = "1".to_owned();
+
+    // write a profile and content with the old code, which predates storage
+    // registration and content items
+    test_contract_call(&creator, &worker, &contract, "add_profile",
+        json!({
+            "account_id": creator.id(),
+            "profile_type": "creator",
+            "cost": "1",
+            "payment_interval": "3"
+        }),
+        "", false
+    ).await.expect("error when adding profile with old code");
+    test_contract_call(&creator, &worker, &contract, "add_content",
+        json!({
+            "date": &content_id,
+            "content": &content
+        }),
+        "", false
+    ).await.expect("error when adding content with old code");
+
+    // deploy the new code with the contracts own key, then migrate the state
+    let new_wasm = get_wasm(DECENTRALISED_CONTENT_SUBSCRIPTION_NEAR_WASM_FILEPATH);
+    contract.as_account()
+        .deploy(&worker, new_wasm.clone())
+        .await?;
+    contract.call(&worker, "migrate")
+        .args_json(json!({}))?
+        .transact()
+        .await
+        .expect("error when migrating with new code");
+
+    test_contract_call_json(&creator, &worker, &contract, "get_profile",
+        json!({
+            "account_id": creator.id()
+        }),
+        json!({
+            "profile_type": "creator",
+            "cost": "1000000000000000000000000",
+            "payment_interval": 3
+        })
+    ).await.expect("error when getting profile after migrate");
+    test_contract_call_json(&creator, &worker, &contract, "get_content",
+        json!({
+            "creator_address": creator.id(),
+            "date": &content_id
+        }),
+        json!({ "body": &content })
+    ).await.expect("error when getting content after migrate");
+
+    // the migrated contract is owned by its own account, which can now upgrade it in place
+    test_contract_call(contract.as_account(), &worker, &contract, "get_owner",
+        json!({}), contract.id().as_str(), true
+    ).await.expect("error when getting owner");
+    contract.as_account()
+        .call(&worker, contract.id().to_owned(), "upgrade")
+        .args(new_wasm)
+        .gas(MAX_GAS)
+        .transact()
+        .await
+        .expect("error when upgrading with owner");
+    let result = creator
+        .call(&worker, contract.id().to_owned(), "upgrade")
+        .args(vec![0, 97, 115, 109])
+        .gas(MAX_GAS)
+        .transact()
+        .await;
+    assert!(result.is_err(), "upgrade with non owner succeeded");
+
+    test_contract_call_json(&creator, &worker, &contract, "get_content",
+        json!({
+            "creator_address": creator.id(),
+            "date": &content_id
+        }),
+        json!({ "body": &content })
+    ).await.expect("error when getting content after upgrade");
+
+    remove_near_credentials();
+
+    Ok(())
+}
@@ -4,36 +4,33 @@ use std::process::Command;
 /// general helper functions
 pub mod utils {
     use super::*;    
-    /// returns file as Vec<u8>
-    /// if file does not exist calls build method then returns once built
-    /// panics if read attempt fails once build method has been called
+    /// builds the contracts then returns the wasm at path_to_wasm as Vec<u8>
+    /// so tests always run against the current code
+    /// panics if the wasm can't be read once built
     pub fn get_wasm(path_to_wasm: &str) -> Vec<u8> {
+        build_contract();
         match std::fs::read(path_to_wasm) {
-            Err(_) => {
-                build_contract();  
-                match std::fs::read(path_to_wasm) {
-                    Ok(wasm) => {
-                        println!("Contract was built, returning wasm");
-                        wasm
-                    },
-                    Err(_) => panic!("could not retrive wasm")
-                }
-            },
             Ok(wasm) => {
-                println!("Contract is already built, returning wasm");
+                println!("Contract was built, returning wasm");
                 wasm
-            }
+            },
+            Err(_) => panic!("could not retrive wasm")
         }
     }
 
     /// iterates through a commands list that builds the wasm
-    /// then copies to contracts/res directory
+    /// then copies to the res directory of each contract, which git ignores
     pub fn build_contract() {
         let commands = vec![
             ("echo", "    Building Contract .wasm file"),
             ("echo", ""),
             ("cargo", "build --target wasm32-unknown-unknown --release"),
+            ("mkdir", "-p contracts/res"),
             ("cp", "target/wasm32-unknown-unknown/release/decentralised_content_subscription_near.wasm contracts/res/decentralised_content_subscription_near.wasm"),
+            ("mkdir", "-p mock-ft/res"),
+            ("cp", "target/wasm32-unknown-unknown/release/mock_ft.wasm mock-ft/res/mock_ft.wasm"),
+            ("mkdir", "-p factory/res"),
+            ("cp", "target/wasm32-unknown-unknown/release/content_subscription_factory.wasm factory/res/content_subscription_factory.wasm"),
             ("echo", "")
         ];
         for tup in commands.iter() {